@@ -0,0 +1,206 @@
+extern crate glutin;
+extern crate serde_json;
+
+use std::collections::HashMap;
+use std::fmt;
+use glutin::{MouseButton, VirtualKeyCode};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Action {
+    Select,
+    Confirm,
+    Cancel,
+    EndTurn,
+    Undo,
+    CycleTarget,
+    Pause,
+    SlowMotion,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    PanDrag,
+    Recentre,
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+}
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Action> {
+        match name {
+            "select" => Some(Action::Select),
+            "confirm" => Some(Action::Confirm),
+            "cancel" => Some(Action::Cancel),
+            "end_turn" => Some(Action::EndTurn),
+            "undo" => Some(Action::Undo),
+            "cycle_target" => Some(Action::CycleTarget),
+            "pause" => Some(Action::Pause),
+            "slow_motion" => Some(Action::SlowMotion),
+            "pan_up" => Some(Action::PanUp),
+            "pan_down" => Some(Action::PanDown),
+            "pan_left" => Some(Action::PanLeft),
+            "pan_right" => Some(Action::PanRight),
+            "pan_drag" => Some(Action::PanDrag),
+            "recentre" => Some(Action::Recentre),
+            "cursor_up" => Some(Action::CursorUp),
+            "cursor_down" => Some(Action::CursorDown),
+            "cursor_left" => Some(Action::CursorLeft),
+            "cursor_right" => Some(Action::CursorRight),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+// keys that can be bound, by the name of their VirtualKeyCode. Escape quits, so it is left out
+const BINDABLE_KEYS: [VirtualKeyCode; 65] = [
+    VirtualKeyCode::A, VirtualKeyCode::B, VirtualKeyCode::C, VirtualKeyCode::D, VirtualKeyCode::E, VirtualKeyCode::F,
+    VirtualKeyCode::G, VirtualKeyCode::H, VirtualKeyCode::I, VirtualKeyCode::J, VirtualKeyCode::K, VirtualKeyCode::L,
+    VirtualKeyCode::M, VirtualKeyCode::N, VirtualKeyCode::O, VirtualKeyCode::P, VirtualKeyCode::Q, VirtualKeyCode::R,
+    VirtualKeyCode::S, VirtualKeyCode::T, VirtualKeyCode::U, VirtualKeyCode::V, VirtualKeyCode::W, VirtualKeyCode::X,
+    VirtualKeyCode::Y, VirtualKeyCode::Z,
+    VirtualKeyCode::Key0, VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3, VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5, VirtualKeyCode::Key6, VirtualKeyCode::Key7, VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+    VirtualKeyCode::F1, VirtualKeyCode::F2, VirtualKeyCode::F3, VirtualKeyCode::F4, VirtualKeyCode::F5, VirtualKeyCode::F6,
+    VirtualKeyCode::F7, VirtualKeyCode::F8, VirtualKeyCode::F9, VirtualKeyCode::F10, VirtualKeyCode::F11, VirtualKeyCode::F12,
+    VirtualKeyCode::Up, VirtualKeyCode::Down, VirtualKeyCode::Left, VirtualKeyCode::Right,
+    VirtualKeyCode::Space, VirtualKeyCode::Return, VirtualKeyCode::Back, VirtualKeyCode::Tab,
+    VirtualKeyCode::Delete, VirtualKeyCode::LShift, VirtualKeyCode::RShift, VirtualKeyCode::LControl, VirtualKeyCode::RControl,
+    VirtualKeyCode::LAlt, VirtualKeyCode::RAlt, VirtualKeyCode::Home, VirtualKeyCode::End,
+];
+
+impl Binding {
+    /**
+     * Parses a key by its VirtualKeyCode name, like "E" or "Return", or a mouse button as "MouseLeft",
+     * "MouseRight" or "MouseMiddle".
+     */
+    pub fn from_name(name: &str) -> Option<Binding> {
+        match name {
+            "MouseLeft" => Some(Binding::Mouse(MouseButton::Left)),
+            "MouseRight" => Some(Binding::Mouse(MouseButton::Right)),
+            "MouseMiddle" => Some(Binding::Mouse(MouseButton::Middle)),
+            _ => BINDABLE_KEYS.iter()
+                .find(|key| format!("{:?}", key) == name)
+                .map(|key| Binding::Key(*key)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingError {
+    Parse(serde_json::Error),
+    UnknownAction(String),
+    UnknownBinding(String, String),
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BindingError::Parse(ref err) => write!(f, "{}", err),
+            BindingError::UnknownAction(ref action) => write!(f, "unknown action \"{}\"", action),
+            BindingError::UnknownBinding(ref action, ref binding) => write!(f, "\"{}\" has an unknown binding \"{}\"", action, binding),
+        }
+    }
+}
+
+/**
+ * Keys and mouse buttons bound to each action. Any of an action's bindings being held holds the action.
+ */
+#[derive(Debug)]
+pub struct ActionMap {
+    pub bindings: HashMap<Action, Vec<Binding>>,
+}
+
+impl ActionMap {
+    pub fn new() -> ActionMap {
+        let mut action_map = ActionMap{ bindings: HashMap::new() };
+        action_map.rebind(Action::Select, vec![Binding::Mouse(MouseButton::Left)]);
+        action_map.rebind(Action::Confirm, vec![Binding::Key(VirtualKeyCode::Return), Binding::Key(VirtualKeyCode::Space)]);
+        action_map.rebind(Action::Cancel, vec![Binding::Mouse(MouseButton::Right), Binding::Key(VirtualKeyCode::Back)]);
+        action_map.rebind(Action::EndTurn, vec![Binding::Key(VirtualKeyCode::E)]);
+        action_map.rebind(Action::Undo, vec![Binding::Key(VirtualKeyCode::Z)]);
+        action_map.rebind(Action::CycleTarget, vec![Binding::Key(VirtualKeyCode::Tab)]);
+        action_map.rebind(Action::Pause, vec![Binding::Key(VirtualKeyCode::P)]);
+        action_map.rebind(Action::SlowMotion, vec![Binding::Key(VirtualKeyCode::T)]);
+        action_map.rebind(Action::PanUp, vec![Binding::Key(VirtualKeyCode::W)]);
+        action_map.rebind(Action::PanDown, vec![Binding::Key(VirtualKeyCode::S)]);
+        action_map.rebind(Action::PanLeft, vec![Binding::Key(VirtualKeyCode::A)]);
+        action_map.rebind(Action::PanRight, vec![Binding::Key(VirtualKeyCode::D)]);
+        action_map.rebind(Action::PanDrag, vec![Binding::Mouse(MouseButton::Left), Binding::Mouse(MouseButton::Middle)]);
+        action_map.rebind(Action::Recentre, vec![Binding::Mouse(MouseButton::Middle), Binding::Key(VirtualKeyCode::Home)]);
+        action_map.rebind(Action::CursorUp, vec![Binding::Key(VirtualKeyCode::Up)]);
+        action_map.rebind(Action::CursorDown, vec![Binding::Key(VirtualKeyCode::Down)]);
+        action_map.rebind(Action::CursorLeft, vec![Binding::Key(VirtualKeyCode::Left)]);
+        action_map.rebind(Action::CursorRight, vec![Binding::Key(VirtualKeyCode::Right)]);
+        action_map
+    }
+
+    /**
+     * The default bindings, with the actions in the json object replaced. Each key of the object is an
+     * action name like "end_turn", and its value the list of binding names.
+     */
+    pub fn from_json(data: &str) -> Result<ActionMap, BindingError> {
+        let names: HashMap<String, Vec<String>> = serde_json::from_str(data).map_err(BindingError::Parse)?;
+        let mut action_map = ActionMap::new();
+        for (action_name, binding_names) in names {
+            let action = Action::from_name(&action_name).ok_or_else(|| BindingError::UnknownAction(action_name.clone()))?;
+            let mut bindings = Vec::new();
+            for binding_name in binding_names {
+                match Binding::from_name(&binding_name) {
+                    Some(binding) => bindings.push(binding),
+                    None => return Err(BindingError::UnknownBinding(action_name, binding_name)),
+                }
+            }
+            action_map.rebind(action, bindings);
+        }
+        Ok(action_map)
+    }
+
+    /**
+     * Replaces the bindings of the action.
+     */
+    pub fn rebind(&mut self, action: Action, bindings: Vec<Binding>) {
+        self.bindings.insert(action, bindings);
+    }
+
+    pub fn get_bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], |bindings| bindings.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let action_map = ActionMap::from_json(r#"{ "end_turn": ["Return", "MouseMiddle"], "undo": [] }"#).unwrap();
+        assert_eq!(action_map.get_bindings(Action::EndTurn), &[Binding::Key(VirtualKeyCode::Return), Binding::Mouse(MouseButton::Middle)]);
+        assert!(action_map.get_bindings(Action::Undo).is_empty());
+        // actions left out keep their defaults
+        assert_eq!(action_map.get_bindings(Action::Select), &[Binding::Mouse(MouseButton::Left)]);
+
+        match ActionMap::from_json(r#"{ "jump": ["Space"] }"#) {
+            Err(BindingError::UnknownAction(action)) => assert_eq!(action, "jump"),
+            _ => panic!("expected an unknown action"),
+        }
+        match ActionMap::from_json(r#"{ "undo": ["Ctrl-Z"] }"#) {
+            Err(BindingError::UnknownBinding(action, binding)) => assert_eq!((action.as_ref(), binding.as_ref()), ("undo", "Ctrl-Z")),
+            _ => panic!("expected an unknown binding"),
+        }
+    }
+
+    #[test]
+    fn test_escape_not_bindable() {
+        // escape always quits, so binding it would quit instead
+        assert_eq!(Binding::from_name("Escape"), None);
+        assert_eq!(Binding::from_name("Return"), Some(Binding::Key(VirtualKeyCode::Return)));
+    }
+}
@@ -1,15 +1,28 @@
 extern crate specs;
 
-use std::collections::HashMap;
-use specs::{Component, VecStorage};
-use std::time::Instant;
+use std::collections::{HashMap, HashSet};
+use specs::{Component, Entity, VecStorage};
+use spritesheet::TaggedAnimation;
 
 pub struct AnimationSheet {
     pub animations: HashMap<String, Vec<String>>,
     pub current_animation: String,
     pub current_index: usize,
     pub frame_time: f32,
-    pub time_passed: Instant,
+    // seconds each frame shows for, for animations with their own timing instead of frame_time
+    pub frame_times: HashMap<String, Vec<f32>>,
+    // seconds the current frame has shown for
+    pub time_passed: f32,
+    // animations that play once and hold their last frame, instead of looping
+    pub one_shots: HashSet<String>,
+    // set once a one shot animation has shown its last frame for a frame time
+    pub finished: bool,
+    // event names sent when an animation reaches a frame, keyed by the animation and frame index
+    pub frame_events: HashMap<(String, usize), String>,
+    // whether the current frame was just reached, and its event hasn't been sent yet
+    pub entered_frame: bool,
+    // frame tags named "<prefix>/<animation>" belong to this sheet, none are taken without a prefix
+    pub tag_prefix: Option<String>,
 }
 
 impl AnimationSheet {
@@ -19,7 +32,13 @@ impl AnimationSheet {
             current_animation: String::new(),
             current_index: 0,
             frame_time: frame_time,
-            time_passed: Instant::now(),
+            frame_times: HashMap::new(),
+            time_passed: 0.0,
+            one_shots: HashSet::new(),
+            finished: false,
+            frame_events: HashMap::new(),
+            entered_frame: false,
+            tag_prefix: None,
         }
     }
 
@@ -27,6 +46,45 @@ impl AnimationSheet {
         self.animations.insert(name, frames);
     }
 
+    pub fn set_tag_prefix(&mut self, prefix: &str) {
+        self.tag_prefix = Some(prefix.to_string());
+    }
+
+    /**
+     * Adds the tagged animations with this sheet's prefix, named without it, and their frame times.
+     * Ones with the same name as an existing animation replace it.
+     */
+    pub fn add_tagged_animations(&mut self, tagged_animations: &[TaggedAnimation]) {
+        let prefix = match self.tag_prefix {
+            Some(ref prefix) => format!("{}/", prefix),
+            None => return,
+        };
+        for tagged_animation in tagged_animations {
+            let name = match tagged_animation.name.strip_prefix(&prefix) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+            self.frame_times.insert(name.clone(), tagged_animation.frame_times.clone());
+            self.animations.insert(name.clone(), tagged_animation.frames.clone());
+            // the replaced animation may have been longer
+            if name == self.current_animation {
+                self.set_current_animation(name);
+            }
+        }
+    }
+
+    pub fn add_one_shot_animation(&mut self, name: String, frames: Vec<String>) {
+        self.one_shots.insert(name.clone());
+        self.animations.insert(name, frames);
+    }
+
+    /**
+     * Sends the event when the animation reaches the frame, for systems to react to, like releasing an arrow.
+     */
+    pub fn add_frame_event(&mut self, animation: &str, frame: usize, event: &str) {
+        self.frame_events.insert((animation.to_string(), frame), event.to_string());
+    }
+
     pub fn get_current_animation(&self) -> &Vec<String> {
         self.animations.get(&self.current_animation).unwrap()
     }
@@ -36,12 +94,85 @@ impl AnimationSheet {
             .get(self.current_index).unwrap()
     }
 
+    pub fn get_frame_time(&self) -> f32 {
+        self.frame_times.get(&self.current_animation)
+            .and_then(|frame_times| frame_times.get(self.current_index))
+            .cloned()
+            .unwrap_or(self.frame_time)
+    }
+
+    pub fn get_frame_event(&self) -> Option<&String> {
+        self.frame_events.get(&(self.current_animation.clone(), self.current_index))
+    }
+
     pub fn set_current_animation(&mut self, frame_name: String) {
         self.current_animation = frame_name;
         self.current_index = 0;
+        self.time_passed = 0.0;
+        self.finished = false;
+        self.entered_frame = true;
+    }
+
+    /**
+     * Moves on to the next frame, going back to the first at the end of a looping animation.
+     * One shots stay on their last frame and are marked finished instead.
+     */
+    pub fn next_frame(&mut self) {
+        if self.current_index + 1 < self.get_current_animation().len() {
+            self.current_index += 1;
+            self.entered_frame = true;
+        } else if self.one_shots.contains(&self.current_animation) {
+            self.finished = true;
+        } else {
+            self.current_index = 0;
+            self.entered_frame = true;
+        }
     }
 }
 
 impl Component for AnimationSheet {
     type Storage = VecStorage<AnimationSheet>;
-}
\ No newline at end of file
+}
+
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub name: String,
+}
+
+/**
+ * Frame events sent by the animation system this step, cleared when it next runs. Systems reading
+ * them must depend on "animation_system" in the dispatcher, or they can miss a step's events.
+ */
+pub struct AnimationEvents(pub Vec<AnimationEvent>);
+
+impl Component for AnimationEvents {
+    type Storage = specs::HashMapStorage<AnimationEvents>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_tagged_animations() {
+        let tagged_animation = |name: &str, frame: &str| TaggedAnimation{
+            name: name.to_string(),
+            frames: vec![frame.to_string()],
+            frame_times: vec![0.2],
+        };
+        let tagged_animations = vec![tagged_animation("player/idle", "player_idle.png"), tagged_animation("skeleton/idle", "skeleton_idle.png"), tagged_animation("idle", "idle.png")];
+
+        let mut animation_sheet = AnimationSheet::new(0.1);
+        animation_sheet.add_animation("idle".to_string(), vec!["skeleton_1.png".to_string()]);
+        animation_sheet.set_current_animation("idle".to_string());
+        // without a prefix, nothing is taken
+        animation_sheet.add_tagged_animations(&tagged_animations);
+        assert_eq!(animation_sheet.get_current_frame(), "skeleton_1.png");
+
+        animation_sheet.set_tag_prefix("skeleton");
+        animation_sheet.add_tagged_animations(&tagged_animations);
+        assert_eq!(animation_sheet.animations.len(), 1);
+        assert_eq!(animation_sheet.get_current_frame(), "skeleton_idle.png");
+        assert_eq!(animation_sheet.get_frame_time(), 0.2);
+    }
+}
@@ -0,0 +1,156 @@
+extern crate specs;
+
+use std::collections::HashMap;
+use specs::{Component, HashMapStorage};
+use components::{AnimationSheet, PlayerActionState};
+
+pub struct AnimationState {
+    pub animation: String,
+    // state to go to once a one shot animation finishes, none for looping ones
+    pub fallback: Option<String>,
+}
+
+/**
+ * Picks the entity's animation from named states. States change when the player's action state
+ * does, and one shot states fall back to another state when their animation finishes.
+ */
+pub struct AnimationStateMachine {
+    pub states: HashMap<String, AnimationState>,
+    pub current_state: String,
+    // state entered when the action state changes to the key
+    pub transitions: HashMap<PlayerActionState, String>,
+    last_action_state: Option<PlayerActionState>,
+}
+
+impl AnimationStateMachine {
+    pub fn new() -> AnimationStateMachine {
+        AnimationStateMachine{
+            states: HashMap::new(),
+            current_state: String::new(),
+            transitions: HashMap::new(),
+            last_action_state: None,
+        }
+    }
+
+    pub fn add_state(&mut self, name: &str, animation: &str) {
+        self.states.insert(name.to_string(), AnimationState{ animation: animation.to_string(), fallback: None });
+    }
+
+    /**
+     * Adds a state playing a one shot animation once, then going to the fallback state.
+     */
+    pub fn add_one_shot_state(&mut self, name: &str, animation: &str, fallback: &str) {
+        self.states.insert(name.to_string(), AnimationState{ animation: animation.to_string(), fallback: Some(fallback.to_string()) });
+    }
+
+    pub fn add_transition(&mut self, action_state: PlayerActionState, state: &str) {
+        self.transitions.insert(action_state, state.to_string());
+    }
+
+    /**
+     * Enters the state, starting its animation from the first frame.
+     */
+    pub fn set_state(&mut self, state: &str, animation_sheet: &mut AnimationSheet) {
+        if let Some(animation_state) = self.states.get(state) {
+            self.current_state = state.to_string();
+            animation_sheet.set_current_animation(animation_state.animation.clone());
+        }
+    }
+
+    /**
+     * Follows the transition for the action state if it changed since the last update,
+     * then falls back from a finished one shot.
+     */
+    pub fn update(&mut self, action_state: Option<PlayerActionState>, animation_sheet: &mut AnimationSheet) {
+        if let Some(action_state) = action_state {
+            if self.last_action_state != Some(action_state) {
+                self.last_action_state = Some(action_state);
+                if let Some(state) = self.transitions.get(&action_state).cloned() {
+                    if state != self.current_state {
+                        self.set_state(&state, animation_sheet);
+                    }
+                }
+            }
+        }
+
+        if animation_sheet.finished {
+            let fallback = self.states.get(&self.current_state).and_then(|animation_state| animation_state.fallback.clone());
+            if let Some(fallback) = fallback {
+                self.set_state(&fallback, animation_sheet);
+            }
+        }
+    }
+}
+
+impl Component for AnimationStateMachine {
+    type Storage = HashMapStorage<AnimationStateMachine>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_animations() -> (AnimationStateMachine, AnimationSheet) {
+        let mut animation_sheet = AnimationSheet::new(0.1);
+        animation_sheet.add_animation("idle".to_string(), vec!["idle.png".to_string()]);
+        animation_sheet.add_animation("walk".to_string(), vec!["walk_1.png".to_string(), "walk_2.png".to_string()]);
+        animation_sheet.add_one_shot_animation("jump".to_string(), vec!["jump_1.png".to_string(), "jump_2.png".to_string()]);
+        animation_sheet.add_animation("fall".to_string(), vec!["fall.png".to_string()]);
+
+        let mut state_machine = AnimationStateMachine::new();
+        state_machine.add_state("idle", "idle");
+        state_machine.add_state("walk", "walk");
+        state_machine.add_one_shot_state("jump", "jump", "fall");
+        state_machine.add_state("fall", "fall");
+        state_machine.add_transition(PlayerActionState::OnGround, "idle");
+        state_machine.add_transition(PlayerActionState::Moving, "walk");
+        state_machine.add_transition(PlayerActionState::Jumping, "jump");
+        state_machine.set_state("idle", &mut animation_sheet);
+        (state_machine, animation_sheet)
+    }
+
+    #[test]
+    fn test_transitions() {
+        let (mut state_machine, mut animation_sheet) = get_animations();
+        state_machine.update(Some(PlayerActionState::Moving), &mut animation_sheet);
+        assert_eq!(state_machine.current_state, "walk");
+        assert_eq!(animation_sheet.get_current_frame(), "walk_1.png");
+
+        // staying in the action state doesn't restart the animation
+        animation_sheet.next_frame();
+        state_machine.update(Some(PlayerActionState::Moving), &mut animation_sheet);
+        assert_eq!(animation_sheet.get_current_frame(), "walk_2.png");
+
+        // no transition for in air, so the state stays put
+        state_machine.update(Some(PlayerActionState::InAir), &mut animation_sheet);
+        assert_eq!(state_machine.current_state, "walk");
+    }
+
+    #[test]
+    fn test_one_shot_falls_back() {
+        let (mut state_machine, mut animation_sheet) = get_animations();
+        state_machine.update(Some(PlayerActionState::Jumping), &mut animation_sheet);
+        assert_eq!(state_machine.current_state, "jump");
+
+        animation_sheet.next_frame();
+        assert!(!animation_sheet.finished);
+        // holds the last frame for its frame time before finishing
+        animation_sheet.next_frame();
+        assert!(animation_sheet.finished);
+        assert_eq!(animation_sheet.get_current_frame(), "jump_2.png");
+
+        state_machine.update(Some(PlayerActionState::Jumping), &mut animation_sheet);
+        assert_eq!(state_machine.current_state, "fall");
+        assert_eq!(animation_sheet.get_current_frame(), "fall.png");
+    }
+
+    #[test]
+    fn test_frame_events() {
+        let (_, mut animation_sheet) = get_animations();
+        animation_sheet.add_frame_event("walk", 1, "step");
+        animation_sheet.set_current_animation("walk".to_string());
+        assert_eq!(animation_sheet.get_frame_event(), None);
+        animation_sheet.next_frame();
+        assert_eq!(animation_sheet.get_frame_event().map(|event| event.as_ref()), Some("step"));
+    }
+}
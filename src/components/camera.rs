@@ -1,11 +1,164 @@
 extern crate specs;
 extern crate cgmath;
 
-use specs::Component;
-use cgmath::Matrix4;
+use specs::{Component, Entity};
+use cgmath::{ortho, Matrix4, Vector2};
+use renderer::viewport;
+use renderer::viewport::{ScalingPolicy, Viewport};
 
-pub struct Camera(pub Matrix4<f32>);
+pub const MIN_ZOOM: f32 = 0.5;
+pub const MAX_ZOOM: f32 = 4.0;
+
+#[derive(Clone)]
+pub struct Camera {
+    // bottom left of the view in world space
+    pub pos: Vector2<f32>,
+    // position at the end of the step before last, for drawing in between
+    pub previous_pos: Vector2<f32>,
+    // resolution the game is designed for, the view is this size at a zoom of 1
+    pub design_size: Vector2<f32>,
+    // in the same logical pixels as Input::mouse_pos
+    pub window_size: Vector2<f32>,
+    pub scaling: ScalingPolicy,
+    // above 1 zooms in, showing less of the world
+    pub zoom: f32,
+    // entity the camera follows
+    pub target: Option<Entity>,
+    // distance from the centre of the view the target can move before the camera follows, on each axis
+    pub dead_zone: Vector2<f32>,
+    // fraction of the way to its goal the camera moves every 60th of a second
+    pub follow_speed: f32,
+}
+
+impl Camera {
+    pub fn new(design_size: [f32; 2], scaling: ScalingPolicy) -> Camera {
+        let design_size = Vector2::new(design_size[0], design_size[1]);
+        Camera{
+            pos: Vector2::new(0.0, 0.0),
+            previous_pos: Vector2::new(0.0, 0.0),
+            design_size,
+            window_size: design_size,
+            scaling,
+            zoom: 1.0,
+            target: None,
+            dead_zone: Vector2::new(96.0, 64.0),
+            follow_speed: 0.1,
+        }
+    }
+
+    /**
+     * Copy of the camera placed alpha of the way from its previous position to its current one.
+     */
+    pub fn interpolate(&self, alpha: f32) -> Camera {
+        let mut camera = self.clone();
+        camera.pos = self.previous_pos + (self.pos - self.previous_pos) * alpha;
+        camera
+    }
+
+    pub fn get_viewport(&self) -> Viewport {
+        viewport::get_viewport(self.window_size, self.design_size, self.scaling)
+    }
+
+    /**
+     * Size of the world area shown in the viewport.
+     */
+    pub fn get_view_size(&self) -> Vector2<f32> {
+        let viewport = self.get_viewport();
+        viewport.size / (viewport.scale * self.zoom)
+    }
+
+    /**
+     * Changes the zoom, keeping the centre of the view in place.
+     */
+    pub fn set_zoom(&mut self, zoom: f32) {
+        let centre = self.pos + self.get_view_size() / 2.0;
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        let pos = centre - self.get_view_size() / 2.0;
+        // shifted the same, so drawing between steps doesn't slide from the old zoom's position
+        self.previous_pos += pos - self.pos;
+        self.pos = pos;
+    }
+
+    // world space area covered by the whole window, including any bars around the viewport
+    fn get_window_bounds(&self, view_pos: Vector2<f32>, zoom: f32) -> (Vector2<f32>, Vector2<f32>) {
+        let viewport = self.get_viewport();
+        let units_per_pixel = 1.0 / (viewport.scale * zoom);
+        (view_pos - viewport.pos * units_per_pixel, self.window_size * units_per_pixel)
+    }
+
+    /**
+     * Projection for drawing the world, covering the whole window.
+     */
+    pub fn get_projection(&self) -> Matrix4<f32> {
+        let (pos, size) = self.get_window_bounds(self.pos, self.zoom);
+        ortho(pos.x, pos.x + size.x, pos.y, pos.y + size.y, 0.0, 1.0)
+    }
+
+    /**
+     * Projection for drawing the ui in design resolution coordinates, which doesn't scroll or zoom.
+     */
+    pub fn get_ui_projection(&self) -> Matrix4<f32> {
+        let (pos, size) = self.get_window_bounds(Vector2::new(0.0, 0.0), 1.0);
+        ortho(pos.x, pos.x + size.x, pos.y, pos.y + size.y, 0.0, 1.0)
+    }
+
+    /**
+     * Projection in window pixels, for the bars around the viewport.
+     */
+    pub fn get_window_projection(&self) -> Matrix4<f32> {
+        ortho(0.0, self.window_size.x, 0.0, self.window_size.y, 0.0, 1.0)
+    }
+
+    // converts a window position (from the top left, y down) into the space the window shows
+    // from view_pos at the zoom (y up)
+    fn screen_to_view(&self, screen_pos: &(i32, i32), view_pos: Vector2<f32>, zoom: f32) -> Vector2<f32> {
+        let (pos, size) = self.get_window_bounds(view_pos, zoom);
+        let units_per_pixel = size.x / self.window_size.x;
+        Vector2::new(
+            pos.x + screen_pos.0 as f32 * units_per_pixel,
+            pos.y + size.y - screen_pos.1 as f32 * units_per_pixel,
+        )
+    }
+
+    /**
+     * Converts a window position (from the top left, y down) to world space (y up).
+     */
+    pub fn screen_to_world(&self, screen_pos: &(i32, i32)) -> Vector2<f32> {
+        self.screen_to_view(screen_pos, self.pos, self.zoom)
+    }
+
+    /**
+     * Converts a window position (from the top left, y down) to the design resolution coordinates the ui is drawn in.
+     */
+    pub fn screen_to_ui(&self, screen_pos: &(i32, i32)) -> Vector2<f32> {
+        self.screen_to_view(screen_pos, Vector2::new(0.0, 0.0), 1.0)
+    }
+}
 
 impl Component for Camera {
     type Storage = specs::HashMapStorage<Camera>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_to_world() {
+        let mut camera = Camera::new([960.0, 640.0], ScalingPolicy::PixelPerfect);
+        camera.pos = Vector2::new(100.0, 50.0);
+        assert_eq!(camera.screen_to_world(&(0, 640)), Vector2::new(100.0, 50.0));
+
+        // doubled, with a 40 pixel bar on the left and 60 on the top and bottom
+        camera.window_size = Vector2::new(2000.0, 1400.0);
+        assert_eq!(camera.screen_to_world(&(40, 1340)), Vector2::new(100.0, 50.0));
+        assert_eq!(camera.screen_to_world(&(1960, 60)), Vector2::new(1060.0, 690.0));
+
+        camera.set_zoom(2.0);
+        assert_eq!(camera.get_view_size(), Vector2::new(480.0, 320.0));
+        // the centre stays put
+        assert_eq!(camera.screen_to_world(&(1000, 700)), Vector2::new(580.0, 370.0));
+        // the ui doesn't scroll or zoom
+        assert_eq!(camera.screen_to_ui(&(40, 1340)), Vector2::new(0.0, 0.0));
+    }
+}
@@ -1,13 +0,0 @@
-use specs::{Component, HashMapStorage};
-
-pub struct EnergyBar;
-
-impl EnergyBar {
-    pub fn get_max_width() -> u16 {
-        150
-    }
-}
-
-impl Component for EnergyBar {
-    type Storage = HashMapStorage<EnergyBar>;
-}
\ No newline at end of file
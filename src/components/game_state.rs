@@ -3,14 +3,27 @@ extern crate specs;
 use specs::Component;
 use types::Turn;
 
+// fraction of the player's unused energy carried into their next turn
+pub const DEFAULT_BANK_RATE: f32 = 0.5;
+
 #[derive(Debug)]
 pub struct GameState{
     pub turn: Turn,
+    // goes up each time the player's turn comes back around, starting at 1
+    pub round: usize,
+    pub bank_rate: f32,
+    // set by the end turn button or action, kept until the player's turn ends
+    pub end_turn_requested: bool,
 }
 
 impl GameState {
     pub fn new() -> GameState {
-        GameState{ turn: Turn::Player }
+        GameState{
+            turn: Turn::Player,
+            round: 1,
+            bank_rate: DEFAULT_BANK_RATE,
+            end_turn_requested: false,
+        }
     }
 }
 
@@ -1,34 +1,273 @@
 extern crate specs;
 extern crate glutin;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 use specs::Component;
-use glutin::VirtualKeyCode;
+use glutin::{MouseButton, VirtualKeyCode};
+use components::{Action, ActionMap, Binding};
+
+// pixels the mouse can move with a button down and still click
+const DRAG_DISTANCE: i32 = 4;
+// milliseconds between the clicks of a double click
+const DOUBLE_CLICK_TIME: u64 = 300;
+
+/**
+ * Something that happened since the last logic step. Actions bound to a mouse button are pressed
+ * when it's clicked, so dragging doesn't select anything.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    KeyPressed(VirtualKeyCode),
+    KeyReleased(VirtualKeyCode),
+    ActionPressed(Action),
+    ActionReleased(Action),
+    Click(MouseButton, (i32, i32)),
+    // sent after the click it finishes
+    DoubleClick(MouseButton, (i32, i32)),
+    // the mouse moved with the button held, from and to in screen space
+    Drag(MouseButton, (i32, i32), (i32, i32)),
+    DragEnd(MouseButton),
+}
+
+#[derive(Debug)]
+struct ButtonPress {
+    pos: (i32, i32),
+    dragging: bool,
+}
 
 #[derive(Debug)]
 pub struct Input {
     pub hidpi_factor: f32,
-    pub pressed_keys: HashMap<VirtualKeyCode, bool>,
+    // keys held down
+    pub pressed_keys: HashSet<VirtualKeyCode>,
     pub mouse_pos: (i32, i32),
-    pub mouse_pressed: bool,
+    pub action_map: ActionMap,
+    // events since the last logic step, systems take the ones they handle so they only happen once
+    pub events: Vec<InputEvent>,
+    // mouse buttons held down, with where they were pressed
+    pressed_buttons: HashMap<MouseButton, ButtonPress>,
+    last_click: Option<(MouseButton, (i32, i32), Instant)>,
 }
 
 impl Input {
-    pub fn new(hidpi_factor: f32, keys: Vec<VirtualKeyCode>) -> Input {
-        let mut key_map: HashMap<VirtualKeyCode, bool> = HashMap::new();
-        for key in keys {
-            key_map.insert(key, false);
-        }
-
+    pub fn new(hidpi_factor: f32, action_map: ActionMap) -> Input {
         Input{
             hidpi_factor: hidpi_factor,
-            pressed_keys: key_map,
+            pressed_keys: HashSet::new(),
             mouse_pos: (0, 0),
-            mouse_pressed: false
+            action_map,
+            events: Vec::new(),
+            pressed_buttons: HashMap::new(),
+            last_click: None,
+        }
+    }
+
+    // actions with the binding
+    fn get_bound_actions(&self, binding: Binding) -> Vec<Action> {
+        self.action_map.bindings.iter()
+            .filter(|&(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+            .collect()
+    }
+
+    /**
+     * Records a key going down. Repeats from the key being held are ignored.
+     */
+    pub fn press_key(&mut self, key: VirtualKeyCode) {
+        if !self.pressed_keys.insert(key) {
+            return
+        }
+        self.events.push(InputEvent::KeyPressed(key));
+        for action in self.get_bound_actions(Binding::Key(key)) {
+            self.events.push(InputEvent::ActionPressed(action));
         }
     }
+
+    pub fn release_key(&mut self, key: VirtualKeyCode) {
+        if !self.pressed_keys.remove(&key) {
+            return
+        }
+        self.events.push(InputEvent::KeyReleased(key));
+        for action in self.get_bound_actions(Binding::Key(key)) {
+            self.events.push(InputEvent::ActionReleased(action));
+        }
+    }
+
+    /**
+     * Records the mouse moving, in logical pixels from the top left of the window. Held buttons start
+     * dragging once the mouse is far enough from where they were pressed.
+     */
+    pub fn move_mouse(&mut self, pos: (i32, i32)) {
+        let last_pos = self.mouse_pos;
+        self.mouse_pos = pos;
+        for (button, press) in self.pressed_buttons.iter_mut() {
+            if !press.dragging && ((pos.0 - press.pos.0).abs() > DRAG_DISTANCE || (pos.1 - press.pos.1).abs() > DRAG_DISTANCE) {
+                press.dragging = true;
+                self.events.push(InputEvent::Drag(*button, press.pos, pos));
+            } else if press.dragging {
+                self.events.push(InputEvent::Drag(*button, last_pos, pos));
+            }
+        }
+    }
+
+    pub fn press_button(&mut self, button: MouseButton) {
+        self.pressed_buttons.insert(button, ButtonPress{ pos: self.mouse_pos, dragging: false });
+    }
+
+    /**
+     * Records a mouse button going up, which is a click unless the mouse was dragged. Two clicks
+     * close together in time and place are also a double click.
+     */
+    pub fn release_button(&mut self, button: MouseButton, now: Instant) {
+        let press = match self.pressed_buttons.remove(&button) {
+            Some(press) => press,
+            None => return,
+        };
+        let actions = self.get_bound_actions(Binding::Mouse(button));
+
+        if press.dragging {
+            self.events.push(InputEvent::DragEnd(button));
+        } else {
+            let pos = self.mouse_pos;
+            self.events.push(InputEvent::Click(button, pos));
+            for action in actions.iter() {
+                self.events.push(InputEvent::ActionPressed(*action));
+            }
+
+            let double_click = match self.last_click {
+                Some((last_button, last_pos, time)) => last_button == button &&
+                    now.duration_since(time) <= Duration::from_millis(DOUBLE_CLICK_TIME) &&
+                    (pos.0 - last_pos.0).abs() <= DRAG_DISTANCE && (pos.1 - last_pos.1).abs() <= DRAG_DISTANCE,
+                None => false,
+            };
+            if double_click {
+                self.events.push(InputEvent::DoubleClick(button, pos));
+                // a third click starts a new double click
+                self.last_click = None;
+            } else {
+                self.last_click = Some((button, pos, now));
+            }
+        }
+
+        for action in actions {
+            self.events.push(InputEvent::ActionReleased(action));
+        }
+    }
+
+    /**
+     * Whether any of the keys or mouse buttons bound to the action are held.
+     */
+    pub fn is_action_pressed(&self, action: Action) -> bool {
+        self.action_map.get_bindings(action).iter().any(|binding| match *binding {
+            Binding::Key(key) => self.pressed_keys.contains(&key),
+            Binding::Mouse(button) => self.pressed_buttons.contains_key(&button),
+        })
+    }
+
+    /**
+     * Whether the action was pressed since the last step, without taking the event.
+     */
+    pub fn was_action_pressed(&self, action: Action) -> bool {
+        self.events.contains(&InputEvent::ActionPressed(action))
+    }
+
+    // removes the first event matching, returning it
+    fn take_event<F>(&mut self, matches: F) -> Option<InputEvent> where F: Fn(&InputEvent) -> bool {
+        let index = self.events.iter().position(matches)?;
+        Some(self.events.remove(index))
+    }
+
+    /**
+     * Takes a press of the action, returning whether there was one.
+     */
+    pub fn take_action(&mut self, action: Action) -> bool {
+        self.take_event(|event| *event == InputEvent::ActionPressed(action)).is_some()
+    }
+
+    pub fn take_key(&mut self, key: VirtualKeyCode) -> bool {
+        self.take_event(|event| *event == InputEvent::KeyPressed(key)).is_some()
+    }
+
+    /**
+     * Takes the drags of the mouse buttons bound to the action, as the screen space positions the
+     * mouse moved between.
+     */
+    pub fn take_drags(&mut self, action: Action) -> Vec<((i32, i32), (i32, i32))> {
+        let bindings = self.action_map.get_bindings(action).to_vec();
+        let mut drags = Vec::new();
+        while let Some(event) = self.take_event(|event| match *event {
+            InputEvent::Drag(button, _, _) => bindings.contains(&Binding::Mouse(button)),
+            _ => false,
+        }) {
+            if let InputEvent::Drag(_, from, to) = event {
+                drags.push((from, to));
+            }
+        }
+        drags
+    }
 }
 
 impl Component for Input {
     type Storage = specs::HashMapStorage<Input>;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_click_and_drag() {
+        let mut input = Input::new(1.0, ActionMap::new());
+        let now = Instant::now();
+        input.move_mouse((100, 100));
+        input.press_button(MouseButton::Left);
+        input.move_mouse((102, 101));
+        input.release_button(MouseButton::Left, now);
+        assert!(input.was_action_pressed(Action::Select));
+        assert!(input.take_action(Action::Select));
+        // taken, so it only happens once
+        assert!(!input.take_action(Action::Select));
+        assert!(!input.events.iter().any(|event| matches!(*event, InputEvent::DoubleClick(..))));
+
+        input.events.clear();
+        input.press_button(MouseButton::Left);
+        input.move_mouse((110, 100));
+        input.move_mouse((120, 90));
+        assert!(input.is_action_pressed(Action::Select));
+        input.release_button(MouseButton::Left, now + Duration::from_millis(100));
+        assert_eq!(input.take_drags(Action::PanDrag), vec![((102, 101), (110, 100)), ((110, 100), (120, 90))]);
+        // dragging isn't a click
+        assert!(!input.take_action(Action::Select));
+        assert!(!input.is_action_pressed(Action::Select));
+    }
+
+    #[test]
+    fn test_double_click() {
+        let mut input = Input::new(1.0, ActionMap::new());
+        let now = Instant::now();
+        for i in 0..3 {
+            input.press_button(MouseButton::Left);
+            input.release_button(MouseButton::Left, now + Duration::from_millis(i * 200));
+        }
+        // the third click starts a new double click rather than finishing another
+        let double_clicks = |input: &Input| input.events.iter().filter(|event| matches!(**event, InputEvent::DoubleClick(..))).count();
+        assert_eq!(double_clicks(&input), 1);
+
+        input.events.clear();
+        input.press_button(MouseButton::Left);
+        input.release_button(MouseButton::Left, now + Duration::from_millis(1000));
+        assert_eq!(double_clicks(&input), 0);
+    }
+
+    #[test]
+    fn test_key_repeat() {
+        let mut input = Input::new(1.0, ActionMap::new());
+        input.press_key(VirtualKeyCode::Return);
+        input.press_key(VirtualKeyCode::Return);
+        assert!(input.take_action(Action::Confirm));
+        assert!(!input.take_action(Action::Confirm));
+        input.release_key(VirtualKeyCode::Return);
+        assert!(input.events.contains(&InputEvent::ActionReleased(Action::Confirm)));
+    }
+}
@@ -1,27 +1,39 @@
+mod action_map;
 mod animation_sheet;
+mod animation_state_machine;
 mod camera;
 mod color;
 mod enemy;
-mod energy_bar;
 mod game_state;
 mod highlight_tile;
 mod input;
+mod particle_emitter;
 mod player;
 mod rect;
+mod screen_space;
 mod sprite;
+mod text;
+mod tile_cursor;
 mod tile_data;
+mod time;
 mod transform;
 
+pub use self::action_map::*;
 pub use self::animation_sheet::*;
+pub use self::animation_state_machine::*;
 pub use self::camera::*;
 pub use self::color::*;
 pub use self::enemy::*;
-pub use self::energy_bar::*;
 pub use self::game_state::*;
 pub use self::highlight_tile::*;
 pub use self::input::*;
+pub use self::particle_emitter::*;
 pub use self::player::*;
 pub use self::rect::*;
+pub use self::screen_space::*;
 pub use self::sprite::*;
+pub use self::text::*;
+pub use self::tile_cursor::*;
 pub use self::tile_data::*;
+pub use self::time::*;
 pub use self::transform::*;
\ No newline at end of file
@@ -0,0 +1,135 @@
+extern crate specs;
+extern crate cgmath;
+
+use cgmath::Vector2;
+use specs::{Component, VecStorage};
+
+pub struct Particle {
+    pub pos: Vector2<f32>,
+    pub velocity: Vector2<f32>,
+    // seconds
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+/**
+ * Spawns particles from the entity's transform. The particles move on their own once spawned,
+ * in world space.
+ */
+pub struct ParticleEmitter {
+    pub frame_name: String,
+    pub size: Vector2<u16>,
+    // particles per second, zero for an emitter that only does bursts
+    pub spawn_rate: f32,
+    // min and max seconds a particle lives for
+    pub lifetime: (f32, f32),
+    // min and max velocity in pixels per second, picked per axis
+    pub velocity: (Vector2<f32>, Vector2<f32>),
+    // pixels per second squared, pulling down
+    pub gravity: f32,
+    // colour at the start and end of a particle's life, blended between
+    pub colors: ([f32; 4], [f32; 4]),
+    // particles to spawn all at once on the next update
+    pub burst: usize,
+    // delete the entity once its burst has been spawned and all its particles are gone
+    pub one_shot: bool,
+    pub particles: Vec<Particle>,
+    // fraction of a particle owed by the spawn rate
+    pub spawn_remainder: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BurstKind {
+    Jump,
+    Land,
+    Step,
+    // nothing deals hits yet, the arrows will push these where they land
+    #[allow(dead_code)]
+    Hit,
+}
+
+impl ParticleEmitter {
+    pub fn new(frame_name: &str) -> ParticleEmitter {
+        ParticleEmitter{
+            frame_name: frame_name.to_string(),
+            size: Vector2::new(4, 4),
+            spawn_rate: 0.0,
+            lifetime: (0.5, 1.0),
+            velocity: (Vector2::new(-50.0, 0.0), Vector2::new(50.0, 100.0)),
+            gravity: 200.0,
+            colors: ([1.0; 4], [1.0, 1.0, 1.0, 0.0]),
+            burst: 0,
+            one_shot: false,
+            particles: Vec::new(),
+            spawn_remainder: 0.0,
+        }
+    }
+
+    /**
+     * One-shot emitter for a burst of the kind of effect.
+     */
+    pub fn new_burst(kind: BurstKind, frame_name: &str) -> ParticleEmitter {
+        let mut emitter = ParticleEmitter::new(frame_name);
+        emitter.one_shot = true;
+        match kind {
+            // dust kicked up and out from the feet
+            BurstKind::Jump => {
+                emitter.burst = 12;
+                emitter.lifetime = (0.3, 0.6);
+                emitter.velocity = (Vector2::new(-80.0, 20.0), Vector2::new(80.0, 90.0));
+                emitter.colors = ([0.8, 0.7, 0.5, 1.0], [0.8, 0.7, 0.5, 0.0]);
+            },
+            // dust spreading along the ground
+            BurstKind::Land => {
+                emitter.burst = 16;
+                emitter.lifetime = (0.3, 0.5);
+                emitter.velocity = (Vector2::new(-120.0, 10.0), Vector2::new(120.0, 50.0));
+                emitter.colors = ([0.8, 0.7, 0.5, 1.0], [0.8, 0.7, 0.5, 0.0]);
+            },
+            // a puff of dust behind a footstep
+            BurstKind::Step => {
+                emitter.burst = 3;
+                emitter.lifetime = (0.15, 0.25);
+                emitter.velocity = (Vector2::new(-30.0, 10.0), Vector2::new(30.0, 30.0));
+                emitter.colors = ([0.8, 0.7, 0.5, 0.6], [0.8, 0.7, 0.5, 0.0]);
+            },
+            // sparks flying out in every direction
+            BurstKind::Hit => {
+                emitter.burst = 10;
+                emitter.lifetime = (0.2, 0.4);
+                emitter.velocity = (Vector2::new(-150.0, -150.0), Vector2::new(150.0, 150.0));
+                emitter.gravity = 0.0;
+                emitter.colors = ([1.0, 0.9, 0.4, 1.0], [1.0, 0.3, 0.1, 0.0]);
+            },
+        }
+        emitter
+    }
+
+    pub fn get_color(&self, particle: &Particle) -> [f32; 4] {
+        let t = (particle.age / particle.lifetime).min(1.0);
+        let (start, end) = self.colors;
+        [
+            start[0] + (end[0] - start[0]) * t,
+            start[1] + (end[1] - start[1]) * t,
+            start[2] + (end[2] - start[2]) * t,
+            start[3] + (end[3] - start[3]) * t,
+        ]
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.one_shot && self.burst == 0 && self.particles.is_empty()
+    }
+}
+
+impl Component for ParticleEmitter {
+    type Storage = VecStorage<ParticleEmitter>;
+}
+
+/**
+ * Bursts requested by other systems, spawned by the particle system as their own entities.
+ */
+pub struct ParticleBursts(pub Vec<(BurstKind, Vector2<f32>)>);
+
+impl Component for ParticleBursts {
+    type Storage = specs::HashMapStorage<ParticleBursts>;
+}
@@ -2,7 +2,7 @@ extern crate specs;
 
 use specs::Component;
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum PlayerActionState {
     OnGround,
     Moving,
@@ -11,12 +11,20 @@ pub enum PlayerActionState {
 }
 
 const BASE_ENERGY: usize = 10;
+// most energy that can be banked from one turn into the next
+const MAX_BANKED_ENERGY: usize = 5;
 
 pub struct Player {
     pub action_state: PlayerActionState,
     pub movement_route: Vec<(usize, usize)>,
+    // seconds towards the next step along the route
+    pub step_timer: f32,
+    // whether the move is down from the air, so a landing burst shows when it ends
+    pub landing: bool,
     pub jump_distance: usize,
     pub energy: usize,
+    // saved from the last turn, added on top of the base energy when the next one starts
+    pub banked_energy: usize,
 }
 
 impl Player {
@@ -24,13 +32,19 @@ impl Player {
         Player{
             action_state: PlayerActionState::OnGround,
             movement_route: Vec::new(),
+            step_timer: 0.0,
+            landing: false,
             jump_distance: 8,
             energy: BASE_ENERGY,
+            banked_energy: 0,
         }
     }
 
+    /**
+     * Energy left as a fraction of the base energy, capped at 1 when there's banked energy on top.
+     */
     pub fn get_energy_percentage(&self) -> f32 {
-        self.energy as f32 / BASE_ENERGY as f32
+        (self.energy as f32 / BASE_ENERGY as f32).min(1.0)
     }
 
     pub fn moving(&self) -> bool {
@@ -45,8 +59,17 @@ impl Player {
         self.action_state == PlayerActionState::InAir
     }
 
-    pub fn reset_energy(&mut self) {
-        self.energy = BASE_ENERGY;
+    /**
+     * Banks the rate of the energy left, rounded down, and spends the rest.
+     */
+    pub fn end_turn(&mut self, bank_rate: f32) {
+        self.banked_energy = ((self.energy as f32 * bank_rate).floor() as usize).min(MAX_BANKED_ENERGY);
+        self.energy = 0;
+    }
+
+    pub fn start_turn(&mut self) {
+        self.energy = BASE_ENERGY + self.banked_energy;
+        self.banked_energy = 0;
     }
 
     pub fn take_energy(&mut self, energy: usize) -> bool {
@@ -63,4 +86,25 @@ impl Player {
 
 impl Component for Player {
     type Storage = specs::HashMapStorage<Player>;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bank_energy() {
+        let mut player = Player::new();
+        player.take_energy(3);
+        player.end_turn(0.5);
+        assert_eq!(player.banked_energy, 3);
+        player.start_turn();
+        assert_eq!(player.energy, 13);
+        assert_eq!(player.get_energy_percentage(), 1.0);
+
+        // a whole unused turn is capped
+        player.end_turn(1.0);
+        player.start_turn();
+        assert_eq!(player.energy, BASE_ENERGY + MAX_BANKED_ENERGY);
+    }
+}
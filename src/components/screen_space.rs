@@ -0,0 +1,8 @@
+use specs::{Component, HashMapStorage};
+
+// component for rects drawn over the world in screen coordinates, instead of moving with the camera
+pub struct ScreenSpace;
+
+impl Component for ScreenSpace {
+    type Storage = HashMapStorage<ScreenSpace>;
+}
@@ -0,0 +1,29 @@
+extern crate specs;
+
+use specs::{Component, VecStorage};
+use font::TextAlign;
+
+/**
+ * Text drawn with the bitmap font from the top left of the transform, aligned within its width.
+ * Colour comes from a Color component if the entity has one.
+ */
+pub struct Text {
+    pub text: String,
+    pub align: TextAlign,
+    // breaks lines between words to fit the transform's width
+    pub wrap: bool,
+}
+
+impl Text {
+    pub fn new(text: &str, align: TextAlign) -> Text {
+        Text{
+            text: text.to_string(),
+            align,
+            wrap: false,
+        }
+    }
+}
+
+impl Component for Text {
+    type Storage = VecStorage<Text>;
+}
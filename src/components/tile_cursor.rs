@@ -0,0 +1,104 @@
+extern crate specs;
+
+use specs::{Component, HashMapStorage};
+use components::TileData;
+
+/**
+ * The tile the player is aiming at, with rows counted from the top of the map. The mouse puts it
+ * on the tile it's over, and the cursor actions move it between tiles the player can move to.
+ */
+#[derive(Debug)]
+pub struct TileCursor {
+    pub tile: (usize, usize),
+}
+
+impl TileCursor {
+    pub fn new(tile: (usize, usize)) -> TileCursor {
+        TileCursor{
+            tile,
+        }
+    }
+
+    /**
+     * Moves to the closest walkable or jump target tile in the direction, one of up, down, left or right
+     * with y going down. Tiles further off to the side than ahead are skipped. Returns false when there
+     * are none, leaving the cursor where it is.
+     */
+    pub fn move_in_direction(&mut self, direction: (i32, i32), tile_data: &TileData) -> bool {
+        let (x, y) = (self.tile.0 as i32, self.tile.1 as i32);
+        let targets = tile_data.walkable_groups.iter()
+            .chain(Some(&tile_data.jump_targets))
+            .flat_map(|mapping| mapping.0.iter().flat_map(|(y, xs)| xs.iter().map(move |x| (*x, *y))));
+
+        let mut closest: Option<((i32, i32), (usize, usize))> = None;
+        for tile in targets {
+            let offset = (tile.0 as i32 - x, tile.1 as i32 - y);
+            let ahead = offset.0 * direction.0 + offset.1 * direction.1;
+            let aside = (offset.0 * direction.1 - offset.1 * direction.0).abs();
+            if ahead <= 0 || aside > ahead {
+                continue
+            }
+            // straight ahead wins over the side, ties broken by position so the pick doesn't depend on hash order
+            let cost = (ahead + aside * 2, aside);
+            let better = match closest {
+                Some((closest_cost, closest_tile)) => cost < closest_cost || (cost == closest_cost && (tile.1, tile.0) < (closest_tile.1, closest_tile.0)),
+                None => true,
+            };
+            if better {
+                closest = Some((cost, tile));
+            }
+        }
+
+        match closest {
+            Some((_, tile)) => {
+                self.tile = tile;
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+impl Component for TileCursor {
+    type Storage = HashMapStorage<TileCursor>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use types::TileMapping;
+
+    #[test]
+    fn test_move_in_direction() {
+        let mut ground = TileMapping(HashMap::new());
+        ground.insert(5, vec![0, 1, 2, 6]);
+        let mut ledge = TileMapping(HashMap::new());
+        ledge.insert(2, vec![3, 4]);
+        let mut jump_targets = TileMapping(HashMap::new());
+        jump_targets.insert(4, vec![2]);
+        let tile_data = TileData{
+            walkable_groups: vec![ground, ledge],
+            map_size: [8, 6],
+            map_dimensions: [256, 192],
+            tile_size: [32, 32],
+            player_group_index: 0,
+            jump_targets,
+            pathable_grid: Vec::new(),
+        };
+
+        let mut cursor = TileCursor::new((2, 5));
+        // skips the gap in the ground
+        assert!(cursor.move_in_direction((1, 0), &tile_data));
+        assert_eq!(cursor.tile, (6, 5));
+        assert!(!cursor.move_in_direction((1, 0), &tile_data));
+        assert_eq!(cursor.tile, (6, 5));
+
+        cursor.tile = (2, 5);
+        // the jump target straight above comes before the ledge off to the side
+        assert!(cursor.move_in_direction((0, -1), &tile_data));
+        assert_eq!(cursor.tile, (2, 4));
+        assert!(cursor.move_in_direction((0, -1), &tile_data));
+        assert_eq!(cursor.tile, (3, 2));
+    }
+}
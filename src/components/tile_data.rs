@@ -4,6 +4,7 @@ use specs::Component;
 use std::collections::HashMap;
 use tiled::Map;
 use cgmath::Vector2;
+use math::astar;
 use types::TileMapping;
 
 #[derive(Debug)]
@@ -14,10 +15,11 @@ pub struct TileData {
     pub tile_size: [i32; 2],
     pub player_group_index: usize,
     pub jump_targets: TileMapping<usize>,
+    pub pathable_grid: Vec<Vec<astar::TileType>>,
 }
 
 impl TileData {
-    pub fn new(walkable_groups: Vec<TileMapping<usize>>, map: &Map, jump_targets: TileMapping<usize>) -> TileData {
+    pub fn new(walkable_groups: Vec<TileMapping<usize>>, map: &Map, jump_targets: TileMapping<usize>, pathable_grid: Vec<Vec<astar::TileType>>) -> TileData {
         TileData{
             walkable_groups: walkable_groups,
             map_size: [map.width as i32, map.height as i32],
@@ -25,6 +27,7 @@ impl TileData {
             tile_size: [map.tile_width as i32, map.tile_height as i32],
             player_group_index: 0,
             jump_targets: jump_targets,
+            pathable_grid: pathable_grid,
         }
     }
 
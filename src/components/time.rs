@@ -0,0 +1,78 @@
+extern crate specs;
+
+use specs::Component;
+
+/**
+ * Game time, advanced once every fixed logic step. Systems read delta instead of timing themselves,
+ * so pausing or changing the time scale affects all of them.
+ */
+#[derive(Debug)]
+pub struct Time {
+    // seconds of game time since the last step, zero while paused
+    pub delta: f32,
+    // seconds of real time since the last step, for what keeps going while paused or slowed like panning
+    pub real_delta: f32,
+    // seconds of game time since the start
+    pub total: f32,
+    // logic steps so far, counting paused ones
+    pub frame_count: u64,
+    pub paused: bool,
+    // multiplies real time, below 1 for slow motion
+    pub time_scale: f32,
+}
+
+impl Time {
+    pub fn new() -> Time {
+        Time{
+            delta: 0.0,
+            real_delta: 0.0,
+            total: 0.0,
+            frame_count: 0,
+            paused: false,
+            time_scale: 1.0,
+        }
+    }
+
+    /**
+     * Moves on to the next step, real_delta seconds of real time after the last one.
+     */
+    pub fn update(&mut self, real_delta: f32) {
+        self.real_delta = real_delta;
+        self.delta = if self.paused {
+            0.0
+        } else {
+            real_delta * self.time_scale.max(0.0)
+        };
+        self.total += self.delta;
+        self.frame_count += 1;
+    }
+}
+
+impl Component for Time {
+    type Storage = specs::HashMapStorage<Time>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        let mut time = Time::new();
+        time.update(0.1);
+        assert_eq!(time.delta, 0.1);
+
+        time.time_scale = 0.5;
+        time.update(0.1);
+        assert_eq!(time.delta, 0.05);
+        assert_eq!(time.total, 0.15);
+
+        // frames still count while paused
+        time.paused = true;
+        time.update(0.1);
+        assert_eq!(time.delta, 0.0);
+        assert_eq!(time.real_delta, 0.1);
+        assert_eq!(time.total, 0.15);
+        assert_eq!(time.frame_count, 3);
+    }
+}
@@ -2,24 +2,61 @@ extern crate specs;
 extern crate cgmath;
 
 use specs::{Component, VecStorage};
-use cgmath::Vector2;
+use cgmath::{Matrix4, Rad, Vector2, Vector3};
 
+#[derive(Clone)]
 pub struct Transform {
     pub pos: Vector2<i32>,
+    // position at the end of the step before last, for drawing in between
+    pub previous_pos: Vector2<i32>,
     pub size: Vector2<u16>,
+    // radians, counter clockwise around the pivot
     pub rotation: f32,
     pub scale: Vector2<f32>,
+    // point rotation and scale happen around, as a fraction of size from the bottom left
+    pub pivot: Vector2<f32>,
+    // higher draws on top, entities with the same z order keep their usual order
+    pub z_order: i32,
+    // mirrors the image horizontally, for characters facing left
+    pub flip_x: bool,
 }
 
 impl Transform {
     pub fn new(x: i32, y: i32, w: u16, h: u16, rotation: f32, scale_x: f32, scale_y: f32) -> Transform {
         Transform{
             pos: Vector2{ x: x, y: y},
+            previous_pos: Vector2::new(x, y),
             size: Vector2{ x: w, y: h },
             rotation: rotation,
             scale: Vector2{ x: scale_x, y: scale_y },
+            pivot: Vector2{ x: 0.5, y: 0.5 },
+            z_order: 0,
+            flip_x: false,
         }
     }
+
+    /**
+     * Copy of the transform placed alpha of the way from its previous position to its current one.
+     */
+    pub fn interpolate(&self, alpha: f32) -> Transform {
+        let mut transform = self.clone();
+        transform.pos = Vector2::new(
+            (self.previous_pos.x as f32 + (self.pos.x - self.previous_pos.x) as f32 * alpha).round() as i32,
+            (self.previous_pos.y as f32 + (self.pos.y - self.previous_pos.y) as f32 * alpha).round() as i32,
+        );
+        transform
+    }
+
+    /**
+     * Matrix taking a point relative to the bottom left of the transform's size to world space.
+     */
+    pub fn get_model_matrix(&self) -> Matrix4<f32> {
+        let pivot = Vector3::new(self.pivot.x * self.size.x as f32, self.pivot.y * self.size.y as f32, 0.0);
+        Matrix4::from_translation(Vector3::new(self.pos.x as f32, self.pos.y as f32, 0.0) + pivot) *
+            Matrix4::from_angle_z(Rad(self.rotation)) *
+            Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, 1.0) *
+            Matrix4::from_translation(-pivot)
+    }
 }
 
 impl Component for Transform {
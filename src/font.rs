@@ -0,0 +1,310 @@
+//! Bitmap fonts in the AngelCode BMFont text format, and laying out text with them.
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Glyph {
+    // region of the atlas, from the top left
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    // from the pen position to the glyph's top left, y going down
+    pub xoffset: f32,
+    pub yoffset: f32,
+    pub xadvance: f32,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FontError {
+    MissingValue(String, String),
+    InvalidValue(String, String),
+    MissingPage,
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FontError::MissingValue(ref tag, ref key) => write!(f, "\"{}\" is missing \"{}\"", tag, key),
+            FontError::InvalidValue(ref tag, ref key) => write!(f, "\"{}\" has an invalid \"{}\"", tag, key),
+            FontError::MissingPage => write!(f, "font has no page image"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/**
+ * A glyph placed by layout, relative to the top left of the text box with y going up.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlyphQuad {
+    // bottom left
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+    // left, top, right, bottom of the atlas region, from 0 to 1
+    pub uv: [f32; 4],
+}
+
+pub struct BitmapFont {
+    pub line_height: f32,
+    pub scale_size: [f32; 2],
+    // atlas image, relative to the font file
+    pub page: String,
+    glyphs: HashMap<char, Glyph>,
+    kernings: HashMap<(char, char), f32>,
+}
+
+// splits a line into its tag and key value pairs, allowing spaces in quoted values
+fn parse_line(line: &str) -> (&str, HashMap<&str, &str>) {
+    let line = line.trim();
+    let tag_end = line.find(' ').unwrap_or(line.len());
+    let mut values = HashMap::new();
+    let mut rest = line[tag_end..].trim_start();
+    while let Some(equals) = rest.find('=') {
+        let key = rest[..equals].trim();
+        let after = &rest[equals + 1..];
+        let (value, remaining) = if let Some(quoted) = after.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+        } else {
+            let end = after.find(' ').unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+        values.insert(key, value);
+        rest = remaining.trim_start();
+    }
+    (&line[..tag_end], values)
+}
+
+fn get_number(tag: &str, values: &HashMap<&str, &str>, key: &str) -> Result<f32, FontError> {
+    let value = values.get(key).ok_or_else(|| FontError::MissingValue(tag.to_string(), key.to_string()))?;
+    value.parse::<f32>().map_err(|_| FontError::InvalidValue(tag.to_string(), key.to_string()))
+}
+
+fn get_char(tag: &str, values: &HashMap<&str, &str>, key: &str) -> Result<char, FontError> {
+    let id = get_number(tag, values, key)?;
+    ::std::char::from_u32(id as u32).ok_or_else(|| FontError::InvalidValue(tag.to_string(), key.to_string()))
+}
+
+impl BitmapFont {
+    pub fn from_fnt(data: &str) -> Result<BitmapFont, FontError> {
+        let mut font = BitmapFont{
+            line_height: 0.0,
+            scale_size: [1.0, 1.0],
+            page: String::new(),
+            glyphs: HashMap::new(),
+            kernings: HashMap::new(),
+        };
+
+        for line in data.lines() {
+            let (tag, values) = parse_line(line);
+            match tag {
+                "common" => {
+                    font.line_height = get_number(tag, &values, "lineHeight")?;
+                    font.scale_size = [get_number(tag, &values, "scaleW")?, get_number(tag, &values, "scaleH")?];
+                },
+                // only single page fonts are supported, so the first page is used
+                "page" if font.page.is_empty() => {
+                    font.page = values.get("file").ok_or_else(|| FontError::MissingValue(tag.to_string(), "file".to_string()))?.to_string();
+                },
+                "char" => {
+                    font.glyphs.insert(get_char(tag, &values, "id")?, Glyph{
+                        x: get_number(tag, &values, "x")?,
+                        y: get_number(tag, &values, "y")?,
+                        width: get_number(tag, &values, "width")?,
+                        height: get_number(tag, &values, "height")?,
+                        xoffset: get_number(tag, &values, "xoffset")?,
+                        yoffset: get_number(tag, &values, "yoffset")?,
+                        xadvance: get_number(tag, &values, "xadvance")?,
+                    });
+                },
+                "kerning" => {
+                    let pair = (get_char(tag, &values, "first")?, get_char(tag, &values, "second")?);
+                    font.kernings.insert(pair, get_number(tag, &values, "amount")?);
+                },
+                _ => {},
+            }
+        }
+
+        if font.page.is_empty() {
+            return Err(FontError::MissingPage)
+        }
+        Ok(font)
+    }
+
+    // characters the font doesn't have show as a question mark, or nothing
+    fn get_glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    fn get_kerning(&self, previous: Option<char>, c: char) -> f32 {
+        previous.and_then(|previous| self.kernings.get(&(previous, c))).cloned().unwrap_or(0.0)
+    }
+
+    /**
+     * Width of a single line of text, from the pen's start to where it ends up.
+     */
+    pub fn measure(&self, line: &str) -> f32 {
+        let mut width = 0.0;
+        let mut previous = None;
+        for c in line.chars() {
+            if let Some(glyph) = self.get_glyph(c) {
+                width += self.get_kerning(previous, c) + glyph.xadvance;
+            }
+            previous = Some(c);
+        }
+        width
+    }
+
+    /**
+     * Splits text into lines at newlines, and between words so no line is wider than max_width.
+     * A word wider than max_width gets a line to itself.
+     */
+    pub fn wrap(&self, text: &str, max_width: Option<f32>) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let max_width = match max_width {
+                Some(max_width) => max_width,
+                None => {
+                    lines.push(paragraph.to_string());
+                    continue
+                },
+            };
+
+            let mut line = String::new();
+            for word in paragraph.split(' ') {
+                if line.is_empty() {
+                    line.push_str(word);
+                    continue
+                }
+                let candidate = format!("{} {}", line, word);
+                if self.measure(&candidate) <= max_width {
+                    line = candidate;
+                } else {
+                    lines.push(line);
+                    line = word.to_string();
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /**
+     * Places the glyphs of the text in a box width wide, growing down from its top left.
+     * Without a width, lines are aligned to the widest of them and don't wrap.
+     */
+    pub fn layout(&self, text: &str, align: TextAlign, width: Option<f32>) -> Vec<GlyphQuad> {
+        let lines = self.wrap(text, width);
+        let box_width = width.unwrap_or_else(|| lines.iter().map(|line| self.measure(line)).fold(0.0, f32::max));
+
+        let mut quads = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            let mut pen_x = match align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => ((box_width - self.measure(line)) / 2.0).floor(),
+                TextAlign::Right => box_width - self.measure(line),
+            };
+            let top = -(i as f32 * self.line_height);
+
+            let mut previous = None;
+            for c in line.chars() {
+                if let Some(glyph) = self.get_glyph(c) {
+                    pen_x += self.get_kerning(previous, c);
+                    if glyph.width > 0.0 && glyph.height > 0.0 {
+                        quads.push(GlyphQuad{
+                            pos: [pen_x + glyph.xoffset, top - glyph.yoffset - glyph.height],
+                            size: [glyph.width, glyph.height],
+                            uv: [
+                                glyph.x / self.scale_size[0],
+                                glyph.y / self.scale_size[1],
+                                (glyph.x + glyph.width) / self.scale_size[0],
+                                (glyph.y + glyph.height) / self.scale_size[1],
+                            ],
+                        });
+                    }
+                    pen_x += glyph.xadvance;
+                }
+                previous = Some(c);
+            }
+        }
+        quads
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_font() -> BitmapFont {
+        BitmapFont::from_fnt(r#"info face="Test Font" size=8
+common lineHeight=10 base=8 scaleW=64 scaleH=32 pages=1
+page id=0 file="test font.png"
+chars count=4
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=4 page=0
+char id=65 x=8 y=0 width=5 height=7 xoffset=0 yoffset=1 xadvance=6 page=0
+char id=66 x=16 y=8 width=5 height=7 xoffset=1 yoffset=1 xadvance=6 page=0
+char id=63 x=24 y=0 width=5 height=7 xoffset=0 yoffset=1 xadvance=6 page=0
+kernings count=1
+kerning first=65 second=66 amount=-1"#).unwrap()
+    }
+
+    #[test]
+    fn test_from_fnt() {
+        let font = get_font();
+        assert_eq!(font.page, "test font.png");
+        assert_eq!(font.line_height, 10.0);
+        assert_eq!(font.glyphs[&'B'].xoffset, 1.0);
+        assert_eq!(font.kernings[&('A', 'B')], -1.0);
+
+        assert_eq!(BitmapFont::from_fnt("common lineHeight=10 scaleW=64 scaleH=32").err(), Some(FontError::MissingPage));
+        assert_eq!(
+            BitmapFont::from_fnt("page id=0 file=\"a.png\"\nchar id=65 x=zero").err(),
+            Some(FontError::InvalidValue("char".to_string(), "x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_measure() {
+        let font = get_font();
+        // kerning pulls B one pixel towards A
+        assert_eq!(font.measure("AB"), 11.0);
+        assert_eq!(font.measure("A A"), 16.0);
+        // unknown characters use the question mark
+        assert_eq!(font.measure("Z"), 6.0);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let font = get_font();
+        assert_eq!(font.wrap("AA AA AA", Some(30.0)), vec!["AA AA", "AA"]);
+        assert_eq!(font.wrap("AAAAAA A\nA", Some(20.0)), vec!["AAAAAA", "A", "A"]);
+        assert_eq!(font.wrap("AA AA AA", None), vec!["AA AA AA"]);
+    }
+
+    #[test]
+    fn test_layout() {
+        let font = get_font();
+        let quads = font.layout("AB\nA", TextAlign::Left, None);
+        assert_eq!(quads.len(), 3);
+        assert_eq!(quads[0], GlyphQuad{ pos: [0.0, -8.0], size: [5.0, 7.0], uv: [8.0 / 64.0, 0.0, 13.0 / 64.0, 7.0 / 32.0] });
+        assert_eq!(quads[1].pos, [6.0, -8.0]);
+        // second line is a line height down
+        assert_eq!(quads[2].pos, [0.0, -18.0]);
+
+        // the spaces have no quads
+        let quads = font.layout("A A", TextAlign::Right, Some(30.0));
+        assert_eq!(quads.len(), 2);
+        assert_eq!(quads[0].pos[0], 14.0);
+
+        let quads = font.layout("AB\nA", TextAlign::Center, None);
+        assert_eq!(quads[2].pos[0], 2.0);
+    }
+}
@@ -10,66 +10,190 @@ extern crate tiled;
 extern crate serde_derive;
 extern crate serde_json;
 extern crate linked_hash_map;
+extern crate image;
+extern crate xml;
 
 use std::collections::HashMap;
+use std::env;
 use std::ops::{DerefMut};
+use std::process;
 
+use cgmath::Vector2;
 use gfx::Device;
-use specs::{Dispatcher, DispatcherBuilder, Join, World};
+use specs::{Dispatcher, DispatcherBuilder, Entity, Join, RunNow, World};
 
 use std::path::Path;
 use std::fs::File;
+use std::time::{Duration, Instant};
 use tiled::parse;
-use glutin::{Event, ElementState, MouseButton, VirtualKeyCode, WindowEvent};
+use glutin::{Event, ElementState, MouseScrollDelta, VirtualKeyCode, WindowEvent};
 use glutin::GlContext;
-use cgmath::Vector2;
 
 mod renderer;
 mod loader;
 mod components;
+mod font;
 mod math;
 mod spritesheet;
 mod systems;
 mod utils;
 mod types;
-use types::TileMapping;
+mod ui;
+mod validate;
 
-use components::{AnimationSheet, Camera, Color, Enemy, EnergyBar, GameState, HighlightTile, Input, Player, Rect, Sprite, TileData, Transform};
+use components::{ActionMap, AnimationEvents, AnimationSheet, AnimationStateMachine, Camera, Color, Enemy, GameState, HighlightTile, Input, ParticleBursts, ParticleEmitter, Player, PlayerActionState, Rect, ScreenSpace, Sprite, Text, TileCursor, TileData, Time, Transform};
 
 use renderer::{ColorFormat, DepthFormat};
+use renderer::viewport::ScalingPolicy;
+
+use font::BitmapFont;
+use spritesheet::{Spritesheet, SpritesheetError};
+use ui::Ui;
+
+const RESOURCES_DIR: &str = "./resources";
+const MAP_FILE: &str = "map.tmx";
+const ASSET_DATA_FILE: &str = "assets.json";
+const ASSET_TEXTURE_FILE: &str = "assets.png";
+const FONT_FILE: &str = "font.fnt";
+const BINDINGS_FILE: &str = "bindings.json";
+
+fn resource_path(file_name: &str) -> String {
+    format!("{}/{}", RESOURCES_DIR, file_name)
+}
+
+// loads the textures the map uses and builds its renderers
+fn load_map_render_data<R, F>(map: &tiled::Map, factory: &mut F, target: &renderer::WindowTargets<R>) -> Result<utils::tiled::MapRenderData<R>, String>
+    where R: gfx::Resources, F: gfx::Factory<R>
+{
+    let tileset = map.tilesets.get(0).unwrap(); // working under the assumption i will only use one tileset
+    let image = tileset.images.get(0).unwrap();
+    let tiles_texture = loader::try_gfx_load_texture(resource_path(&image.source).as_ref(), factory)
+        .map_err(|err| format!("{}: {}", image.source, err))?;
+
+    let map_file = File::open(Path::new(&resource_path(MAP_FILE))).map_err(|err| err.to_string())?;
+    let mut image_layers = Vec::new();
+    for image_layer in utils::tiled::parse_image_layers(map_file) {
+        let texture = loader::try_gfx_load_texture(resource_path(&image_layer.source).as_ref(), factory)
+            .map_err(|err| format!("{}: {}", image_layer.source, err))?;
+        image_layers.push((image_layer, texture));
+    }
+
+    Ok(utils::tiled::get_map_render_data(map, &tiles_texture, &image_layers, factory, target))
+}
 
-use spritesheet::Spritesheet;
+// swaps in the tile data for a reloaded map, keeping the player in place if they are still on ground
+fn reload_tile_data(world: &mut World, map: &tiled::Map) {
+    let mut tile_data = utils::tiled::build_tile_data(map);
+    let spawn_point = utils::tiled::get_spawn_points(map)[0];
+
+    {
+        let mut players = world.write::<Player>();
+        let mut transforms = world.write::<Transform>();
+        for (player, transform) in (&mut players, &mut transforms).join() {
+            if player.in_air() || tile_data.set_player_group_index_from_pos(&transform.pos) {
+                continue
+            }
+            transform.pos = spawn_point;
+            player.action_state = PlayerActionState::OnGround;
+            player.movement_route.clear();
+            if !tile_data.set_player_group_index_from_pos(&spawn_point) {
+                println!("Start position not on ground: {:?}", spawn_point);
+            }
+        }
+    }
+
+    *world.write_resource::<TileData>() = tile_data;
+}
+
+// keeps where everything was before a step, to draw in between it and the step's result
+fn store_previous_positions(world: &World) {
+    let mut transforms = world.write::<Transform>();
+    for transform in (&mut transforms).join() {
+        transform.previous_pos = transform.pos;
+    }
+    let mut camera = world.write_resource::<Camera>();
+    camera.previous_pos = camera.pos;
+}
+
+// adds the animations tagged in an aseprite export to the animation sheets with their prefix
+fn add_tagged_animations(world: &World, spritesheet: &Spritesheet) {
+    let tagged_animations = spritesheet.get_tagged_animations();
+    let mut animation_sheets = world.write::<AnimationSheet>();
+    for animation_sheet in (&mut animation_sheets).join() {
+        animation_sheet.add_tagged_animations(&tagged_animations);
+    }
+}
 
-fn setup_world<'a>(world: &mut World, window: &glutin::Window, walkable_groups: Vec<TileMapping<usize>>, map: &tiled::Map, jump_targets: TileMapping<usize>, pathable_grid: Vec<Vec<math::astar::TileType>>) -> Dispatcher<'a, 'a> {
-    world.add_resource::<Camera>(Camera(renderer::get_ortho()));
-    world.add_resource::<Input>(Input::new(window.hidpi_factor(), vec![VirtualKeyCode::W, VirtualKeyCode::A, VirtualKeyCode::S, VirtualKeyCode::D]));
-    world.add_resource::<TileData>(TileData::new(walkable_groups, map, jump_targets));
+fn load_action_map() -> Result<ActionMap, String> {
+    let data = loader::read_text_from_file(&resource_path(BINDINGS_FILE)).map_err(|err| err.to_string())?;
+    ActionMap::from_json(&data).map_err(|err| err.to_string())
+}
+
+// checks the frames used by sprites, animations and particle bursts are in the spritesheet
+fn find_missing_frames(world: &World, spritesheet: &Spritesheet) -> Vec<SpritesheetError> {
+    let sprites = world.read::<Sprite>();
+    let animation_sheets = world.read::<AnimationSheet>();
+    let burst_frame = systems::BURST_FRAME.to_string();
+
+    let sprite_frames = sprites.join().map(|sprite| &sprite.frame_name);
+    let animation_frames = animation_sheets.join().flat_map(|animation_sheet| animation_sheet.animations.values().flat_map(|frames| frames.iter()));
+    spritesheet.find_missing_frames(sprite_frames.chain(animation_frames).chain(Some(&burst_frame)))
+}
+
+// adds the resources and entities, returning the player
+fn setup_world(world: &mut World, hidpi_factor: f32, action_map: ActionMap, map: &tiled::Map, scaling: ScalingPolicy) -> Entity {
+    world.add_resource::<Camera>(Camera::new(renderer::get_dimensions(), scaling));
+    world.add_resource::<Input>(Input::new(hidpi_factor, action_map));
+    world.add_resource::<TileData>(utils::tiled::build_tile_data(map));
     world.add_resource::<GameState>(GameState::new());
+    world.add_resource::<Time>(Time::new());
+    world.add_resource::<ParticleBursts>(ParticleBursts(Vec::new()));
+    world.add_resource::<AnimationEvents>(AnimationEvents(Vec::new()));
+    world.add_resource::<Ui>(Ui::new());
     world.register::<AnimationSheet>();
+    world.register::<AnimationStateMachine>();
     world.register::<Color>();
     world.register::<Enemy>();
-    world.register::<EnergyBar>();
     world.register::<HighlightTile>();
+    world.register::<ParticleEmitter>();
     world.register::<Rect>();
+    world.register::<ScreenSpace>();
     world.register::<Sprite>();
+    world.register::<Text>();
     world.register::<Transform>();
     world.register::<Player>();
 
-    let player_pos = Vector2::new(0, 64);
+    let player_pos = utils::tiled::get_spawn_points(map)[0];
+
+    // the player only has the one frame so far, the states are there for when it gets more
+    let mut player_animation_sheet = AnimationSheet::new(0.1);
+    player_animation_sheet.set_tag_prefix("player");
+    player_animation_sheet.add_animation(String::from("idle"), vec![String::from("player.png")]);
+    player_animation_sheet.add_animation(String::from("walk"), vec![String::from("player.png")]);
+    player_animation_sheet.add_frame_event("walk", 0, systems::STEP_EVENT);
+    player_animation_sheet.add_one_shot_animation(String::from("jump"), vec![String::from("player.png")]);
+    player_animation_sheet.add_animation(String::from("fall"), vec![String::from("player.png")]);
+    let mut player_state_machine = AnimationStateMachine::new();
+    player_state_machine.add_state("idle", "idle");
+    player_state_machine.add_state("walk", "walk");
+    player_state_machine.add_one_shot_state("jump", "jump", "fall");
+    player_state_machine.add_state("fall", "fall");
+    player_state_machine.add_transition(PlayerActionState::OnGround, "idle");
+    player_state_machine.add_transition(PlayerActionState::Moving, "walk");
+    player_state_machine.add_transition(PlayerActionState::Jumping, "jump");
+    player_state_machine.add_transition(PlayerActionState::InAir, "fall");
+    player_state_machine.set_state("idle", &mut player_animation_sheet);
 
     let player_entity = world.create_entity()
         .with(Transform::new(player_pos.x, player_pos.y, 32, 64, 0.0, 1.0, 1.0))
-        .with(Sprite{ frame_name: String::from("player.png"), visible: true })
+        .with(player_animation_sheet)
+        .with(player_state_machine)
         .with(Player::new())
         .build();
-
-    world.create_entity()
-        .with(Transform::new(800, 610, EnergyBar::get_max_width(), 25, 0.0, 1.0, 1.0))
-        .with(EnergyBar{})
-        .with(Rect{})
-        .with(Color([0.0, 1.0, 0.0, 1.0]));
+    world.write_resource::<Camera>().target = Some(player_entity);
 
     let mut animation_sheet = AnimationSheet::new(0.1);
+    animation_sheet.set_tag_prefix("skeleton");
     animation_sheet.add_animation(String::from("idle"), vec![
         String::from("skeleton_1.png"),
         String::from("skeleton_2.png"),
@@ -86,21 +210,126 @@ fn setup_world<'a>(world: &mut World, window: &glutin::Window, walkable_groups:
         .with(Sprite{ frame_name: String::from("transparenttile.png"), visible: false })
         .with(HighlightTile{});
 
-    let mut tile_data_res = world.write_resource::<TileData>();
-    let mut tile_data = tile_data_res.deref_mut();
-    if !tile_data.set_player_group_index_from_pos(&player_pos) {
-        println!("Start position not on ground: {:?}", player_pos);
-    }
+    let player_tile = {
+        let mut tile_data_res = world.write_resource::<TileData>();
+        let mut tile_data = tile_data_res.deref_mut();
+        if !tile_data.set_player_group_index_from_pos(&player_pos) {
+            println!("Start position not on ground: {:?}", player_pos);
+        }
+        tile_data.get_tile_for_world_position(&player_pos)
+    };
+    // the cursor starts on the player, for playing without a mouse
+    world.add_resource::<TileCursor>(TileCursor::new(player_tile));
+
+    player_entity
+}
 
+fn build_dispatcher<'a>(player_entity: Entity) -> Dispatcher<'a, 'a> {
     DispatcherBuilder::new()
-        .add(systems::PlayerMovement{ pathable_grid: pathable_grid }, "player_movement", &[])
-        .add(systems::ProcessTurn{}, "process_turn", &[])
-        .add(systems::AnimationSystem::new(), "animation_system", &[])
-        .add(systems::EnergyUi{ player_entity: player_entity }, "energy_ui", &["player_movement"])
+        .add(systems::TimeControl{}, "time_control", &[])
+        .add(systems::UiInput{}, "ui_input", &[])
+        .add(systems::CursorMovement::new(), "cursor_movement", &["ui_input"])
+        .add(systems::PlayerMovement::new(), "player_movement", &["cursor_movement"])
+        .add(systems::CameraFollow::new(), "camera_follow", &["player_movement"])
+        .add(systems::AnimationSystem{}, "animation_system", &[])
+        .add(systems::ParticleSystem{}, "particle_system", &["player_movement", "animation_system"])
+        .add(systems::Hud{ player_entity: player_entity }, "hud", &["ui_input", "player_movement"])
+        .add(systems::ProcessTurn{}, "process_turn", &["player_movement", "hud"])
+        .add(systems::NavDebug::new(), "nav_debug", &["player_movement", "camera_follow"])
         .build()
 }
 
+// draws the starting frame with the cpu renderer, without needing a window
+fn draw_software_frame() -> Result<renderer::SoftwareRenderer, String> {
+    let map = tiled::parse_file(Path::new(&resource_path(MAP_FILE))).map_err(|err| format!("{}: {}", MAP_FILE, err))?;
+    let load_texture = |file_name: &str| renderer::load_texture(&resource_path(file_name)).map_err(|err| format!("{}: {}", file_name, err));
+
+    let tiles_texture = load_texture(&map.tilesets[0].images[0].source)?;
+    let map_file = File::open(Path::new(&resource_path(MAP_FILE))).map_err(|err| err.to_string())?;
+    let mut image_layers = Vec::new();
+    for image_layer in utils::tiled::parse_image_layers(map_file) {
+        let texture = load_texture(&image_layer.source)?;
+        image_layers.push((image_layer, texture));
+    }
+
+    let asset_data = loader::read_text_from_file(&resource_path(ASSET_DATA_FILE)).map_err(|err| err.to_string())?;
+    let spritesheet = Spritesheet::from_json(&asset_data).map_err(|err| format!("{}: {}", ASSET_DATA_FILE, err))?;
+    let sprite_texture = load_texture(ASSET_TEXTURE_FILE)?;
+    let font_data = loader::read_text_from_file(&resource_path(FONT_FILE)).map_err(|err| err.to_string())?;
+    let font = BitmapFont::from_fnt(&font_data).map_err(|err| format!("{}: {}", FONT_FILE, err))?;
+    let font_texture = load_texture(&font.page)?;
+
+    let mut world = World::new();
+    let player_entity = setup_world(&mut world, 1.0, ActionMap::new(), &map, ScalingPolicy::Letterbox);
+    add_tagged_animations(&world, &spritesheet);
+    // the ui is built by systems, which haven't run yet
+    systems::Hud{ player_entity }.run_now(&world.res);
+
+    let dim = renderer::get_dimensions();
+    let mut software_renderer = renderer::SoftwareRenderer::new(dim[0] as u32, dim[1] as u32);
+    software_renderer.set_map(renderer::SoftwareMap{ tilemap: map, tiles_texture, image_layers });
+    renderer::draw_frame(&mut software_renderer, &world, &renderer::SceneAssets{
+        spritesheet: &spritesheet,
+        sprite_texture: &sprite_texture,
+        font: &font,
+        font_texture: &font_texture,
+    }, 1.0);
+    Ok(software_renderer)
+}
+
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 1 && args[1] == "validate" {
+        let path = args.get(2).map(|path| path.as_ref()).unwrap_or("./resources/map.tmx");
+        process::exit(validate::run(Path::new(path)));
+    }
+    if args.len() > 1 && args[1] == "screenshot" {
+        let path = args.get(2).map(|path| path.as_ref()).unwrap_or("screenshot.png");
+        match draw_software_frame().and_then(|software_renderer| software_renderer.save(path).map_err(|err| err.to_string())) {
+            Ok(_) => println!("Saved {}", path),
+            Err(err) => {
+                println!("Could not draw screenshot: {}", err);
+                process::exit(1);
+            },
+        }
+        return
+    }
+    // dev mode watches the resources directory and reloads the map and spritesheet when they change
+    let dev_mode = args.iter().any(|arg| arg == "--dev");
+    // how the game fits the window when resized: pixel, letterbox or expand
+    let scaling = match args.iter().position(|arg| arg == "--scaling").and_then(|index| args.get(index + 1)) {
+        Some(name) => match ScalingPolicy::from_name(name) {
+            Some(scaling) => scaling,
+            None => {
+                println!("Unknown scaling policy {}, expected pixel, letterbox or expand", name);
+                process::exit(1);
+            },
+        },
+        None => ScalingPolicy::Letterbox,
+    };
+    // how many times a second the game logic runs, however often frames are drawn
+    let hz = match args.iter().position(|arg| arg == "--hz").and_then(|index| args.get(index + 1)) {
+        Some(value) => match value.parse::<f32>() {
+            Ok(hz) if hz > 0.0 => hz,
+            _ => {
+                println!("Invalid --hz {}, expected a positive number", value);
+                process::exit(1);
+            },
+        },
+        None => utils::fixed_step::DEFAULT_HZ,
+    };
+    // fraction of unused energy carried into the player's next turn, 0 to turn banking off
+    let bank_rate = match args.iter().position(|arg| arg == "--bank").and_then(|index| args.get(index + 1)) {
+        Some(value) => match value.parse::<f32>() {
+            Ok(bank_rate) if (0.0..=1.0).contains(&bank_rate) => bank_rate,
+            _ => {
+                println!("Invalid --bank {}, expected a number from 0 to 1", value);
+                process::exit(1);
+            },
+        },
+        None => components::DEFAULT_BANK_RATE,
+    };
+
     let mut events_loop = glutin::EventsLoop::new();
     let dim = renderer::get_dimensions();
     let builder = glutin::WindowBuilder::new()
@@ -114,63 +343,180 @@ fn main() {
 
     let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
 
-    let map_file = File::open(&Path::new("./resources/map.tmx")).unwrap();
-    let map = parse(map_file).unwrap();
+    let map_file = File::open(Path::new(&resource_path(MAP_FILE))).unwrap();
+    let mut map = parse(map_file).unwrap();
 
-    let target = renderer::WindowTargets{
+    let mut target = renderer::WindowTargets{
         color: main_color,
         depth: main_depth,
     };
 
     let mut basic = renderer::Basic::new(&mut factory, &target);
 
-    let tileset = map.tilesets.get(0).unwrap(); // working under the assumption i will only use one tileset
-    let image = tileset.images.get(0).unwrap();
-    let tiles_texture = loader::gfx_load_texture(format!("./resources/{}", image.source).as_ref(), &mut factory);
+    let mut tile_map_render_data = load_map_render_data(&map, &mut factory, &target).unwrap();
 
-    let mut tile_map_render_data = utils::tiled::get_map_render_data(&map, &tiles_texture, &mut factory, &target);
-    let (walkable_groups, jump_targets, unpassable_tiles) = utils::tiled::parse_out_map_layers(&map);
+    let action_map = match load_action_map() {
+        Ok(action_map) => action_map,
+        Err(err) => {
+            println!("{}: {}", BINDINGS_FILE, err);
+            process::exit(1);
+        },
+    };
 
     let mut world = World::new();
-    let pathable_grid: Vec<Vec<math::astar::TileType>> = math::astar::build_grid_for_map(&unpassable_tiles, map.width as usize, map.height as usize);
-    let mut dispatcher = setup_world(&mut world, &window, walkable_groups, &map, jump_targets, pathable_grid);
+    let player_entity = setup_world(&mut world, window.hidpi_factor(), action_map, &map, scaling);
+    world.write_resource::<GameState>().bank_rate = bank_rate;
+    let mut dispatcher = build_dispatcher(player_entity);
+
+    let asset_data = loader::read_text_from_file(&resource_path(ASSET_DATA_FILE)).unwrap();
+    let mut spritesheet = Spritesheet::from_json(asset_data.as_ref()).unwrap();
+    add_tagged_animations(&world, &spritesheet);
+    let missing_frames = find_missing_frames(&world, &spritesheet);
+    if !missing_frames.is_empty() {
+        for err in missing_frames {
+            println!("{}: {}", ASSET_DATA_FILE, err);
+        }
+        process::exit(1);
+    }
+    let mut asset_texture = loader::gfx_load_texture(&resource_path(ASSET_TEXTURE_FILE), &mut factory);
+
+    let font_data = loader::read_text_from_file(&resource_path(FONT_FILE)).unwrap();
+    let font = match BitmapFont::from_fnt(&font_data) {
+        Ok(font) => font,
+        Err(err) => {
+            println!("{}: {}", FONT_FILE, err);
+            process::exit(1);
+        },
+    };
+    let font_texture = loader::gfx_load_texture(&resource_path(&font.page), &mut factory);
 
-    let asset_data = loader::read_text_from_file("./resources/assets.json").unwrap();
-    let spritesheet: Spritesheet = serde_json::from_str(asset_data.as_ref()).unwrap();
-    let asset_texture = loader::gfx_load_texture("./resources/assets.png", &mut factory);
+    let mut watcher = if dev_mode {
+        Some(utils::watcher::FileWatcher::new(RESOURCES_DIR, Duration::from_millis(500)))
+    } else {
+        None
+    };
 
+    let mut fixed_step = utils::fixed_step::FixedStep::new(hz);
+    let mut last_frame = Instant::now();
     let mut running = true;
     while running {
+        if let Some(ref mut watcher) = watcher {
+            let changed: Vec<String> = watcher.poll().iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .collect();
+            let is_changed = |file_name: &str| changed.iter().any(|name| name == file_name);
+            // tileset images are referenced by the map, so they reload it too
+            let tileset_changed = changed.iter().any(|name| name.ends_with(".png") && name != ASSET_TEXTURE_FILE && *name != font.page);
+
+            if is_changed(MAP_FILE) || tileset_changed {
+                match tiled::parse_file(Path::new(&resource_path(MAP_FILE))) {
+                    Ok(reloaded) => {
+                        let image_layers = File::open(Path::new(&resource_path(MAP_FILE)))
+                            .map(utils::tiled::parse_image_layers)
+                            .unwrap_or_default();
+                        // when only tiles changed, just their lookup entries get re-uploaded
+                        let updated = if !tileset_changed && tile_map_render_data.update_tiles(&map, &reloaded, &image_layers) {
+                            true
+                        } else {
+                            match load_map_render_data(&reloaded, &mut factory, &target) {
+                                Ok(render_data) => {
+                                    tile_map_render_data = render_data;
+                                    true
+                                },
+                                Err(err) => {
+                                    println!("Could not reload map images: {}", err);
+                                    false
+                                },
+                            }
+                        };
+
+                        if updated {
+                            reload_tile_data(&mut world, &reloaded);
+                            map = reloaded;
+                            println!("Reloaded {}", MAP_FILE);
+                        }
+                    },
+                    Err(err) => println!("Could not reload {}: {}", MAP_FILE, err),
+                }
+            }
+
+            if is_changed(ASSET_DATA_FILE) {
+                match loader::read_text_from_file(&resource_path(ASSET_DATA_FILE)).map(|data| Spritesheet::from_json(data.as_ref())) {
+                    Ok(Ok(reloaded)) => {
+                        add_tagged_animations(&world, &reloaded);
+                        for err in find_missing_frames(&world, &reloaded) {
+                            println!("{}: {}", ASSET_DATA_FILE, err);
+                        }
+                        spritesheet = reloaded;
+                        println!("Reloaded {}", ASSET_DATA_FILE);
+                    },
+                    Ok(Err(err)) => println!("Could not reload {}: {}", ASSET_DATA_FILE, err),
+                    Err(err) => println!("Could not reload {}: {}", ASSET_DATA_FILE, err),
+                }
+            }
+
+            if is_changed(BINDINGS_FILE) {
+                match load_action_map() {
+                    Ok(action_map) => {
+                        world.write_resource::<Input>().action_map = action_map;
+                        println!("Reloaded {}", BINDINGS_FILE);
+                    },
+                    Err(err) => println!("Could not reload {}: {}", BINDINGS_FILE, err),
+                }
+            }
+
+            if is_changed(ASSET_TEXTURE_FILE) {
+                match loader::try_gfx_load_texture(&resource_path(ASSET_TEXTURE_FILE), &mut factory) {
+                    Ok(texture) => {
+                        asset_texture = texture;
+                        println!("Reloaded {}", ASSET_TEXTURE_FILE);
+                    },
+                    Err(err) => println!("Could not reload {}: {}", ASSET_TEXTURE_FILE, err),
+                }
+            }
+        }
+
         events_loop.poll_events(|event| {
             match event {
                 Event::WindowEvent{ event, .. } => match event {
+                    WindowEvent::Resized(width, height) => {
+                        window.resize(width, height);
+                        gfx_window_glutin::update_views(&window, &mut target.color, &mut target.depth);
+                        basic.set_target(&target);
+                        tile_map_render_data.set_target(&target);
+                        let mut camera = world.write_resource::<Camera>();
+                        let hidpi_factor = window.hidpi_factor();
+                        camera.window_size = Vector2::new(width as f32 / hidpi_factor, height as f32 / hidpi_factor);
+                    },
+                    WindowEvent::MouseWheel{ delta, .. } => {
+                        let lines = match delta {
+                            MouseScrollDelta::LineDelta(_, y) => y,
+                            MouseScrollDelta::PixelDelta(_, y) => y / 32.0,
+                        };
+                        let mut camera = world.write_resource::<Camera>();
+                        let zoom = camera.zoom * (1.0 + lines * 0.1);
+                        camera.set_zoom(zoom);
+                    },
                     WindowEvent::MouseMoved{ position: (x, y), .. } => {
-                        let mut input_res = world.write_resource::<Input>();
-                        let mut input = input_res.deref_mut();
-                        input.mouse_pos.0 = (x as f32 / input.hidpi_factor) as i32;
-                        input.mouse_pos.1 = (y as f32 / input.hidpi_factor) as i32;
+                        let mut input = world.write_resource::<Input>();
+                        let pos = ((x as f32 / input.hidpi_factor) as i32, (y as f32 / input.hidpi_factor) as i32);
+                        input.move_mouse(pos);
                     },
-                    WindowEvent::MouseInput{ button: MouseButton::Left, state, .. } => {
-                        let mut input_res = world.write_resource::<Input>();
-                        let mut input = input_res.deref_mut();
+                    WindowEvent::MouseInput{ button, state, .. } => {
+                        let mut input = world.write_resource::<Input>();
                         match state {
-                            ElementState::Pressed => input.mouse_pressed = true,
-                            ElementState::Released => input.mouse_pressed = false,
+                            ElementState::Pressed => input.press_button(button),
+                            ElementState::Released => input.release_button(button, Instant::now()),
                         };
                     },
                     WindowEvent::KeyboardInput{ input: glutin::KeyboardInput{ virtual_keycode: Some(VirtualKeyCode::Escape), .. }, .. } | glutin::WindowEvent::Closed => running = false,
-                    WindowEvent::KeyboardInput{ input, .. } => {
-                        let input_event = input;
-                        let mut input_res = world.write_resource::<Input>();
-                        let mut input = input_res.deref_mut();
-                        if let Some(key) = input_event.virtual_keycode {
-                            if input.pressed_keys.contains_key(&key) {
-                                match input_event.state {
-                                    ElementState::Pressed => input.pressed_keys.insert(key, true),
-                                    ElementState::Released => input.pressed_keys.insert(key, false),
-                                };
-                            }
-                        }
+                    WindowEvent::KeyboardInput{ input: glutin::KeyboardInput{ virtual_keycode: Some(key), state, .. }, .. } => {
+                        let mut input = world.write_resource::<Input>();
+                        match state {
+                            ElementState::Pressed => input.press_key(key),
+                            ElementState::Released => input.release_key(key),
+                        };
                     },
                     _ => {}
                 },
@@ -178,35 +524,36 @@ fn main() {
             }
         });
 
-        dispatcher.dispatch(&mut world.res);
-
-        basic.reset_transform();
-
-        encoder.clear(&target.color, [16.0 / 256.0, 14.0 / 256.0, 22.0 / 256.0, 1.0]);
-        encoder.clear_depth(&target.depth, 1.0);
-
-        for plane_renderer in tile_map_render_data.iter_mut() {
-            plane_renderer.render(&mut encoder, &mut world);
+        let now = Instant::now();
+        let steps = fixed_step.advance(utils::math::get_mills(&(now - last_frame)));
+        last_frame = now;
+
+        for _ in 0..steps {
+            store_previous_positions(&world);
+            world.write_resource::<Time>().update(fixed_step.step);
+            dispatcher.dispatch(&mut world.res);
+            // removes finished particle bursts
+            world.maintain();
+            // events nothing took are dropped, so they can't fire on a later step
+            world.write_resource::<Input>().events.clear();
         }
 
-        let sprites = world.read::<Sprite>();
-        let transforms = world.read::<Transform>();
-        let animation_sheets = world.read::<AnimationSheet>();
-        let colors = world.read::<Color>();
-        let rects = world.read::<Rect>();
-
-        for (sprite, transform) in (&sprites, &transforms).join() {
-            if sprite.visible {
-                basic.render(&mut encoder, &world, &mut factory, &transform, Some(&sprite.frame_name), &spritesheet, None, Some(&asset_texture));
-            }
-        }
-
-        for (animation_sheet, transform) in (&animation_sheets, &transforms).join() {
-            basic.render(&mut encoder, &world, &mut factory, &transform, Some(animation_sheet.get_current_frame()), &spritesheet, None, Some(&asset_texture));
-        }
+        basic.reset_transform();
 
-        for (color, transform, _) in (&colors, &transforms, &rects).join() {
-            basic.render(&mut encoder, &world, &mut factory, &transform, None, &spritesheet, Some(color.0), None);
+        {
+            let mut gfx_renderer = renderer::GfxRenderer{
+                basic: &mut basic,
+                encoder: &mut encoder,
+                factory: &mut factory,
+                target: &target,
+                map: &mut tile_map_render_data,
+            };
+            renderer::draw_frame(&mut gfx_renderer, &world, &renderer::SceneAssets{
+                spritesheet: &spritesheet,
+                sprite_texture: &asset_texture,
+                font: &font,
+                font_texture: &font_texture,
+            }, fixed_step.get_alpha());
         }
 
         encoder.flush(&mut device);
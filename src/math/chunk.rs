@@ -0,0 +1,103 @@
+//! Splits tile layers into fixed size chunks, so only the chunks in view get drawn
+//! and each chunk's tile lookup fits in the tilemap shader's buffer.
+
+pub const CHUNK_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Chunk {
+    // first column and row, counted from the top left like tiled's layer data
+    pub col: usize,
+    pub row: usize,
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl Chunk {
+    /**
+     * Returns the bottom left world position and size of the chunk, with y going up from the bottom of the map.
+     */
+    pub fn get_world_bounds(&self, map_rows: usize, tile_size: [f32; 2]) -> ([f32; 2], [f32; 2]) {
+        (
+            [self.col as f32 * tile_size[0], (map_rows - self.row - self.rows) as f32 * tile_size[1]],
+            [self.cols as f32 * tile_size[0], self.rows as f32 * tile_size[1]],
+        )
+    }
+}
+
+/**
+ * Returns the chunks covering a map, in rows from the top left. Chunks on the right and bottom edges are cut short.
+ */
+pub fn get_chunks(map_cols: usize, map_rows: usize, chunk_size: usize) -> Vec<Chunk> {
+    let mut chunks: Vec<Chunk> = Vec::new();
+    for row in (0..map_rows).step_by(chunk_size) {
+        for col in (0..map_cols).step_by(chunk_size) {
+            chunks.push(Chunk{
+                col,
+                row,
+                cols: chunk_size.min(map_cols - col),
+                rows: chunk_size.min(map_rows - row),
+            });
+        }
+    }
+    chunks
+}
+
+/**
+ * Index into get_chunks of the chunk containing the tile.
+ */
+pub fn get_chunk_index(x: usize, y: usize, map_cols: usize, chunk_size: usize) -> usize {
+    let chunks_wide = map_cols.div_ceil(chunk_size);
+    (y / chunk_size) * chunks_wide + x / chunk_size
+}
+
+/**
+ * Whether two rects overlap. Rects that only share an edge don't.
+ */
+pub fn intersects(pos: [f32; 2], size: [f32; 2], other_pos: [f32; 2], other_size: [f32; 2]) -> bool {
+    pos[0] < other_pos[0] + other_size[0] && other_pos[0] < pos[0] + size[0] &&
+        pos[1] < other_pos[1] + other_size[1] && other_pos[1] < pos[1] + size[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_chunks() {
+        let chunks = get_chunks(30, 20, 16);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0], Chunk{ col: 0, row: 0, cols: 16, rows: 16 });
+        assert_eq!(chunks[1], Chunk{ col: 16, row: 0, cols: 14, rows: 16 });
+        assert_eq!(chunks[3], Chunk{ col: 16, row: 16, cols: 14, rows: 4 });
+    }
+
+    #[test]
+    fn test_get_chunk_index() {
+        let chunks = get_chunks(30, 20, 16);
+        for &(x, y) in [(0, 0), (17, 3), (5, 18), (29, 19), (15, 16)].iter() {
+            let index = get_chunk_index(x, y, 30, 16);
+            let chunk = &chunks[index];
+            assert!(x >= chunk.col && x < chunk.col + chunk.cols && y >= chunk.row && y < chunk.row + chunk.rows);
+        }
+        assert_eq!(get_chunk_index(17, 3, 30, 16), 1);
+        assert_eq!(get_chunk_index(5, 18, 30, 16), 2);
+    }
+
+    #[test]
+    fn test_get_world_bounds() {
+        let chunks = get_chunks(30, 20, 16);
+        // the top row of chunks is at the top of the map, as y goes up
+        assert_eq!(chunks[0].get_world_bounds(20, [32.0, 32.0]), ([0.0, 128.0], [512.0, 512.0]));
+        assert_eq!(chunks[3].get_world_bounds(20, [32.0, 32.0]), ([512.0, 0.0], [448.0, 128.0]));
+    }
+
+    #[test]
+    fn test_intersects() {
+        let view_pos = [0.0, 0.0];
+        let view_size = [960.0, 640.0];
+        assert!(intersects([512.0, 0.0], [448.0, 128.0], view_pos, view_size));
+        assert!(intersects([-100.0, -100.0], [101.0, 101.0], view_pos, view_size));
+        assert!(!intersects([960.0, 0.0], [512.0, 512.0], view_pos, view_size));
+        assert!(!intersects([0.0, -512.0], [512.0, 512.0], view_pos, view_size));
+    }
+}
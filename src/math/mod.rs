@@ -1 +1,2 @@
-pub mod astar;
\ No newline at end of file
+pub mod astar;
+pub mod chunk;
\ No newline at end of file
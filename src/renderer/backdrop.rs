@@ -0,0 +1,115 @@
+extern crate gfx;
+extern crate cgmath;
+
+use cgmath::{Matrix4, SquareMatrix};
+use gfx::texture;
+
+use renderer;
+use renderer::WindowTargets;
+use utils::tiled::ImageLayer;
+use super::tiled::{pipe, Projection, Vertex};
+
+/**
+ * Returns the positions and uvs of a quad covering the area the projection shows, with the image's
+ * top left placed at its offset from the top of the map, scrolled by its parallax factor.
+ * The uvs go past 0 to 1 so the image repeats.
+ */
+pub fn get_backdrop_quad(image_layer: &ImageLayer, map_height: f32, proj: &Matrix4<f32>) -> [([f32; 2], [f32; 2]); 4] {
+    let image_size = [image_layer.width.max(1) as f32, image_layer.height.max(1) as f32];
+    let (view_pos, view_size) = renderer::get_view_bounds(proj);
+    let (x, y, w, h) = (view_pos.x, view_pos.y, view_size.x, view_size.y);
+
+    let tx = (x * image_layer.parallax - image_layer.offset[0]) / image_size[0];
+    let tx2 = tx + w / image_size[0];
+    let ty2 = (map_height - image_layer.offset[1] - y * image_layer.parallax) / image_size[1];
+    let ty = ty2 - h / image_size[1];
+
+    [
+        ([x, y], [tx, ty2]),
+        ([x + w, y], [tx2, ty2]),
+        ([x + w, y + h], [tx2, ty]),
+        ([x, y + h], [tx, ty]),
+    ]
+}
+
+/**
+ * Draws an image layer repeated across the whole view, scrolled by its parallax factor.
+ */
+pub struct BackdropRenderer<R: gfx::Resources> {
+    pso: gfx::PipelineState<R, pipe::Meta>,
+    projection: Projection,
+    params: pipe::Data<R>,
+    slice: gfx::Slice<R>,
+    image_layer: ImageLayer,
+    map_height: f32,
+}
+
+impl<R> BackdropRenderer<R>
+    where R: gfx::Resources
+{
+    pub fn new<F>(factory: &mut F, image_layer: &ImageLayer, map_height: f32, texture: &gfx::handle::ShaderResourceView<R, [f32; 4]>, target: &WindowTargets<R>) -> BackdropRenderer<R>
+        where F: gfx::Factory<R>
+    {
+        use gfx::traits::FactoryExt;
+
+        let pso = factory.create_pipeline_simple(
+            include_bytes!("shaders/basic.glslv"),
+            include_bytes!("shaders/basic.glslf"),
+            pipe::new()
+        ).unwrap();
+
+        // vertices change with the camera every frame
+        let vbuf = factory.create_buffer(4, gfx::buffer::Role::Vertex, gfx::memory::Usage::Dynamic, gfx::Bind::empty()).unwrap();
+        let index_data: [u32; 6] = [0, 1, 2, 2, 3, 0];
+        let slice = gfx::Slice{
+            start: 0,
+            end: index_data.len() as u32,
+            base_vertex: 0,
+            instances: None,
+            buffer: factory.create_index_buffer(&index_data[..]),
+        };
+
+        let sinfo = texture::SamplerInfo::new(texture::FilterMethod::Bilinear, texture::WrapMode::Tile);
+
+        BackdropRenderer{
+            pso,
+            projection: Projection{
+                model: Matrix4::identity().into(),
+                proj: renderer::get_ortho().into(),
+            },
+            params: pipe::Data{
+                vbuf,
+                projection_cb: factory.create_constant_buffer(1),
+                tex: (texture.clone(), factory.create_sampler(sinfo)),
+                out: target.color.clone(),
+            },
+            slice,
+            image_layer: image_layer.clone(),
+            map_height,
+        }
+    }
+
+    pub fn set_target(&mut self, target: &WindowTargets<R>) {
+        self.params.out = target.color.clone();
+    }
+
+    pub fn render<C>(&mut self,
+        encoder: &mut gfx::Encoder<R, C>,
+        proj: Matrix4<f32>)
+        where R: gfx::Resources, C: gfx::CommandBuffer<R>
+    {
+        self.projection.proj = proj.into();
+
+        let color = [1.0, 1.0, 1.0, self.image_layer.opacity];
+        let corners = get_backdrop_quad(&self.image_layer, self.map_height, &proj);
+        let mut data = [Vertex{ pos: [0.0; 2], uv: [0.0; 2], color }; 4];
+        for (vertex, &(pos, uv)) in data.iter_mut().zip(corners.iter()) {
+            vertex.pos = pos;
+            vertex.uv = uv;
+        }
+
+        encoder.update_buffer(&self.params.vbuf, &data, 0).unwrap();
+        encoder.update_constant_buffer(&self.params.projection_cb, &self.projection);
+        encoder.draw(&self.slice, &self.pso, &self.params);
+    }
+}
@@ -2,13 +2,15 @@ extern crate gfx;
 extern crate cgmath;
 extern crate specs;
 
-use specs::World;
 use renderer::{ColorFormat, DepthFormat};
-use cgmath::{SquareMatrix, Matrix4, Vector3};
+use cgmath::{SquareMatrix, Matrix4, Vector4};
 use gfx::traits::FactoryExt;
 use gfx::texture;
 use components;
-use spritesheet::{Frame, Spritesheet};
+use font::BitmapFont;
+use spritesheet::Frame;
+use utils::tiled::MapRenderData;
+use super::scene::Renderer;
 use renderer::get_ortho;
 
 gfx_defines!{
@@ -37,11 +39,124 @@ pub struct WindowTargets<R: gfx::Resources> {
     pub depth: gfx::handle::DepthStencilView<R, DepthFormat>,
 }
 
+// quads the vertex buffer starts out fitting, it grows when a frame needs more
+const INITIAL_QUAD_CAPACITY: usize = 256;
+
+/**
+ * Builds the vertices for a sprite covering the transform, placed by its model matrix.
+ * Without a frame the whole texture is used. Trimmed frames are placed back where they were in
+ * their source image, and frames packed rotated (90 degrees clockwise) are turned back upright.
+ */
+pub fn get_quad(transform: &components::Transform, frame: Option<&Frame>, sheet_size: [f32; 2], color: [f32; 4]) -> [Vertex; 4] {
+    let w = transform.size.x as f32;
+    let h = transform.size.y as f32;
+
+    // corners of the quad, and of the region in the sheet, from the top left
+    let (mut x, mut y, mut x2, mut y2) = (0.0, 0.0, w, h);
+    let mut region = [0.0, 0.0, 1.0, 1.0];
+    let mut rotated = false;
+
+    if let Some(frame) = frame {
+        let (sw, sh) = (sheet_size[0], sheet_size[1]);
+        rotated = frame.rotated;
+        // rotated frames take up their height across the sheet
+        let (region_w, region_h) = if rotated { (frame.frame.h, frame.frame.w) } else { (frame.frame.w, frame.frame.h) };
+        region = [
+            frame.frame.x as f32 / sw,
+            frame.frame.y as f32 / sh,
+            (frame.frame.x + region_w) as f32 / sw,
+            (frame.frame.y + region_h) as f32 / sh,
+        ];
+
+        if frame.trimmed {
+            let scale_x = w / frame.source_size.w.max(1) as f32;
+            let scale_y = h / frame.source_size.h.max(1) as f32;
+            let trim = &frame.sprite_source_size;
+            x = trim.x as f32 * scale_x;
+            y = trim.y as f32 * scale_y;
+            x2 = (trim.x + trim.w) as f32 * scale_x;
+            y2 = (trim.y + trim.h) as f32 * scale_y;
+        }
+    }
+
+    // uv of a point on the sprite, as fractions across and down it
+    let flip_x = transform.flip_x;
+    let uv = |u: f32, v: f32| {
+        let u = if flip_x { 1.0 - u } else { u };
+        let (across, down) = if rotated { (1.0 - v, u) } else { (u, v) };
+        [region[0] + (region[2] - region[0]) * across, region[1] + (region[3] - region[1]) * down]
+    };
+
+    let model = transform.get_model_matrix();
+    // y goes up from the bottom of the transform
+    let corner = |x: f32, y: f32| {
+        let pos = model * Vector4::new(x, h - y, 0.0, 1.0);
+        [pos.x, pos.y]
+    };
+
+    [
+        Vertex{ pos: corner(x, y2), uv: uv(0.0, 1.0), color },
+        Vertex{ pos: corner(x2, y2), uv: uv(1.0, 1.0), color },
+        Vertex{ pos: corner(x2, y), uv: uv(1.0, 0.0), color },
+        Vertex{ pos: corner(x, y), uv: uv(0.0, 0.0), color },
+    ]
+}
+
+/**
+ * Builds the vertices for each glyph of the text, laid out from the top left of the transform.
+ */
+pub fn get_text_quads(transform: &components::Transform, font: &BitmapFont, text: &components::Text, color: [f32; 4]) -> Vec<[Vertex; 4]> {
+    let width = if text.wrap { Some(transform.size.x as f32) } else { None };
+    let model = transform.get_model_matrix();
+    let corner = |x: f32, y: f32| {
+        let pos = model * Vector4::new(x, transform.size.y as f32 + y, 0.0, 1.0);
+        [pos.x, pos.y]
+    };
+
+    font.layout(&text.text, text.align, width).iter().map(|glyph| {
+        let (x, y, w, h) = (glyph.pos[0], glyph.pos[1], glyph.size[0], glyph.size[1]);
+        let (tx, ty, tx2, ty2) = (glyph.uv[0], glyph.uv[1], glyph.uv[2], glyph.uv[3]);
+        [
+            Vertex{ pos: corner(x, y), uv: [tx, ty2], color },
+            Vertex{ pos: corner(x + w, y), uv: [tx2, ty2], color },
+            Vertex{ pos: corner(x + w, y + h), uv: [tx2, ty], color },
+            Vertex{ pos: corner(x, y + h), uv: [tx, ty], color },
+        ]
+    }).collect()
+}
+
+fn get_index_data(quads: usize) -> Vec<u32> {
+    let mut index_data: Vec<u32> = Vec::with_capacity(quads * 6);
+    for i in 0..quads as u32 {
+        let index = i * 4;
+        index_data.extend_from_slice(&[index, index + 1, index + 2, index + 2, index + 3, index]);
+    }
+    index_data
+}
+
+// run of quads drawn with the same texture
+struct Batch<R: gfx::Resources> {
+    texture: Option<gfx::handle::ShaderResourceView<R, [f32; 4]>>,
+    start: usize,
+    quads: usize,
+}
+
+/**
+ * Sprite batch renderer. Quads are collected with add_quad, and drawn with one
+ * buffer upload and a draw call per change of texture when flushed.
+ */
 pub struct Basic<R: gfx::Resources> {
     pso: gfx::PipelineState<R, pipe::Meta>,
     projection: Projection,
+    projection_cb: gfx::handle::Buffer<R, Projection>,
     target: WindowTargets<R>,
     color_texture: (gfx::handle::ShaderResourceView<R, [f32; 4]>, gfx::handle::Sampler<R>),
+    sampler: gfx::handle::Sampler<R>,
+    vbuf: gfx::handle::Buffer<R, Vertex>,
+    index_buffer: gfx::IndexBuffer<R>,
+    capacity: usize,
+    vertices: Vec<Vertex>,
+    batches: Vec<Batch<R>>,
 }
 
 impl<R> Basic<R>
@@ -50,8 +165,6 @@ impl<R> Basic<R>
     pub fn new<F>(factory: &mut F, target: &WindowTargets<R>) -> Basic<R>
         where F: gfx::Factory<R>
     {
-        use gfx::traits::FactoryExt;
-
         let pso = factory.create_pipeline_simple(
             include_bytes!("shaders/basic.glslv"),
             include_bytes!("shaders/basic.glslf"),
@@ -67,106 +180,239 @@ impl<R> Basic<R>
             texture::FilterMethod::Bilinear,
         texture::WrapMode::Clamp);
 
+        let (vbuf, index_buffer) = Basic::create_buffers(factory, INITIAL_QUAD_CAPACITY);
+
         Basic{
             pso: pso,
             projection: Projection{
                 model: Matrix4::identity().into(),
                 proj: get_ortho().into(),
             },
+            projection_cb: factory.create_constant_buffer(1),
             target: (*target).clone(),
             color_texture: (texture_view, factory.create_sampler(sinfo)),
+            sampler: factory.create_sampler_linear(),
+            vbuf,
+            index_buffer,
+            capacity: INITIAL_QUAD_CAPACITY,
+            vertices: Vec::with_capacity(INITIAL_QUAD_CAPACITY * 4),
+            batches: Vec::new(),
         }
     }
 
+    fn create_buffers<F>(factory: &mut F, quads: usize) -> (gfx::handle::Buffer<R, Vertex>, gfx::IndexBuffer<R>)
+        where F: gfx::Factory<R>
+    {
+        let vbuf = factory.create_buffer(quads * 4, gfx::buffer::Role::Vertex, gfx::memory::Usage::Dynamic, gfx::Bind::empty()).unwrap();
+        let index_buffer = factory.create_index_buffer(&get_index_data(quads)[..]);
+        (vbuf, index_buffer)
+    }
+
+    /**
+     * Call after the window targets are recreated, such as on resize.
+     */
+    pub fn set_target(&mut self, target: &WindowTargets<R>) {
+        self.target = target.clone();
+    }
+
     pub fn reset_transform(&mut self) {
         self.projection.model = Matrix4::identity().into();
     }
 
-    pub fn render<C, F>(&mut self,
+    /**
+     * Queues a quad for drawing. Without a texture it is filled with its vertex colour.
+     */
+    pub fn add_quad(&mut self, texture: Option<&gfx::handle::ShaderResourceView<R, [f32; 4]>>, quad: [Vertex; 4]) {
+        let same_texture = self.batches.last().is_some_and(|batch| batch.texture.as_ref() == texture);
+        if same_texture {
+            self.batches.last_mut().unwrap().quads += 1;
+        } else {
+            self.batches.push(Batch{
+                texture: texture.cloned(),
+                start: self.vertices.len() / 4,
+                quads: 1,
+            });
+        }
+        self.vertices.extend_from_slice(&quad);
+    }
+
+    /**
+     * Draws the queued quads in the order they were added with the projection, and clears the queue.
+     */
+    pub fn flush<C, F>(&mut self,
         encoder: &mut gfx::Encoder<R, C>,
-        world: &World,
-        factory: &mut F,
-        transform: &components::Transform,
-        frame_name: Option<&String>,
-        spritesheet: &Spritesheet,
-        color: Option<[f32; 4]>,
-        texture: Option<&gfx::handle::ShaderResourceView<R, [f32; 4]>>)
-        where R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Factory<R>
+        proj: Matrix4<f32>,
+        factory: &mut F)
+        where C: gfx::CommandBuffer<R>, F: gfx::Factory<R>
     {
-        use std::ops::Deref;
-
-        let camera_res = world.read_resource::<components::Camera>();
-        let camera = camera_res.deref();
-        let x = transform.pos.x as f32;
-        let y = transform.pos.y as f32;
-        let w = transform.size.x as f32;
-        let h = transform.size.y as f32;
-
-        let mut tx = 0.0;
-        let mut ty = 0.0;
-        let mut tx2 = 1.0;
-        let mut ty2 = 1.0;
-
-        if let Some(frame_name) = frame_name {
-            let region = spritesheet.frames.iter().filter(|frame|
-                frame.filename == *frame_name
-            ).collect::<Vec<&Frame>>()[0];
-            let sw = spritesheet.meta.size.w as f32;
-            let sh = spritesheet.meta.size.h as f32;
-            tx = region.frame.x as f32 / sw;
-            ty = region.frame.y as f32 / sh;
-            tx2 = (region.frame.x as f32 + region.frame.w as f32) / sw;
-            ty2 = (region.frame.y as f32 + region.frame.h as f32) / sh;
+        if self.vertices.is_empty() {
+            return
         }
 
-        let tex: (gfx::handle::ShaderResourceView<R, [f32; 4]>, gfx::handle::Sampler<R>) = if let Some(texture) = texture {
-            (texture.clone(), factory.create_sampler_linear())
-        } else {
-            self.color_texture.clone()
-        };
+        let quads = self.vertices.len() / 4;
+        if quads > self.capacity {
+            self.capacity = quads.next_power_of_two();
+            let (vbuf, index_buffer) = Basic::create_buffers(factory, self.capacity);
+            self.vbuf = vbuf;
+            self.index_buffer = index_buffer;
+        }
+
+        self.projection.proj = proj.into();
+
+        encoder.update_buffer(&self.vbuf, &self.vertices, 0).unwrap();
+        encoder.update_constant_buffer(&self.projection_cb, &self.projection);
+
+        for batch in self.batches.iter() {
+            let tex = if let Some(ref texture) = batch.texture {
+                (texture.clone(), self.sampler.clone())
+            } else {
+                self.color_texture.clone()
+            };
 
-        let color = if let Some(color) = color {
-            color
+            let params = pipe::Data{
+                vbuf: self.vbuf.clone(),
+                projection_cb: self.projection_cb.clone(),
+                tex,
+                out: self.target.color.clone(),
+            };
+
+            let slice = gfx::Slice{
+                start: (batch.start * 6) as u32,
+                end: ((batch.start + batch.quads) * 6) as u32,
+                base_vertex: 0,
+                instances: None,
+                buffer: self.index_buffer.clone(),
+            };
+
+            encoder.draw(&slice, &self.pso, &params);
+        }
+
+        self.vertices.clear();
+        self.batches.clear();
+    }
+}
+
+/**
+ * Renderer drawing through gfx for a frame, with the sprite batch and the map's renderers.
+ */
+pub struct GfxRenderer<'a, R: gfx::Resources + 'a, C: gfx::CommandBuffer<R> + 'a, F: gfx::Factory<R> + 'a> {
+    pub basic: &'a mut Basic<R>,
+    pub encoder: &'a mut gfx::Encoder<R, C>,
+    pub factory: &'a mut F,
+    pub target: &'a WindowTargets<R>,
+    pub map: &'a mut MapRenderData<R>,
+}
+
+impl<'a, R, C, F> Renderer for GfxRenderer<'a, R, C, F>
+    where R: gfx::Resources, C: gfx::CommandBuffer<R>, F: gfx::Factory<R>
+{
+    type Texture = gfx::handle::ShaderResourceView<R, [f32; 4]>;
+
+    fn clear(&mut self, color: [f32; 4]) {
+        self.encoder.clear(&self.target.color, color);
+        self.encoder.clear_depth(&self.target.depth, 1.0);
+    }
+
+    fn add_quad(&mut self, texture: Option<&Self::Texture>, quad: [Vertex; 4]) {
+        self.basic.add_quad(texture, quad);
+    }
+
+    fn flush(&mut self, proj: Matrix4<f32>) {
+        self.basic.flush(self.encoder, proj, self.factory);
+    }
+
+    fn draw_map_layers(&mut self, proj: Matrix4<f32>, foreground: bool) {
+        if foreground {
+            for tilemap_renderer in self.map.foreground.iter_mut() {
+                tilemap_renderer.render(self.encoder, proj);
+            }
         } else {
-            [1.0; 4]
+            for backdrop in self.map.backdrops.iter_mut() {
+                backdrop.render(self.encoder, proj);
+            }
+            for tilemap_renderer in self.map.background.iter_mut() {
+                tilemap_renderer.render(self.encoder, proj);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spritesheet::{FrameData, Size};
+
+    #[test]
+    fn test_get_quad() {
+        let transform = components::Transform::new(10, 20, 32, 64, 0.0, 1.0, 1.0);
+        let frame = Frame{
+            filename: "player.png".to_string(),
+            frame: FrameData{ x: 32, y: 0, w: 32, h: 64 },
+            rotated: false,
+            trimmed: false,
+            sprite_source_size: FrameData{ x: 0, y: 0, w: 32, h: 64 },
+            source_size: Size{ w: 32, h: 64 },
+            duration: None,
         };
 
-        let data: Vec<Vertex> = vec![
-            Vertex{
-                pos: [x, y],
-                uv: [tx, ty2],
-                color: color,
-            },
-            Vertex{
-                pos: [x + w, y],
-                uv: [tx2, ty2],
-                color: color,
-            },
-            Vertex{
-                pos: [x + w, y + h],
-                uv: [tx2, ty],
-                color: color,
-            },
-            Vertex{
-                pos: [x, y + h],
-                uv: [tx, ty],
-                color: color,
-            }
-        ];
+        let quad = get_quad(&transform, Some(&frame), [128.0, 128.0], [1.0; 4]);
+        let positions: Vec<[f32; 2]> = quad.iter().map(|vertex| vertex.pos).collect();
+        assert_eq!(positions, vec![[10.0, 20.0], [42.0, 20.0], [42.0, 84.0], [10.0, 84.0]]);
+        // the bottom of the quad samples the bottom of the frame
+        let uvs: Vec<[f32; 2]> = quad.iter().map(|vertex| vertex.uv).collect();
+        assert_eq!(uvs, vec![[0.25, 0.5], [0.5, 0.5], [0.5, 0.0], [0.25, 0.0]]);
 
-        let index_data: Vec<u32> = vec![0, 1, 2, 2, 3, 0];
-        let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&data, &index_data[..]);
+        let quad = get_quad(&transform, None, [128.0, 128.0], [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(quad[1].uv, [1.0, 1.0]);
+        assert_eq!(quad[0].color, [0.0, 1.0, 0.0, 1.0]);
+    }
 
-        let params = pipe::Data{
-            vbuf: vbuf,
-            projection_cb: factory.create_constant_buffer(1),
-            tex: tex,
-            out: self.target.color.clone(),
+    #[test]
+    fn test_get_quad_trimmed_and_rotated_frame() {
+        let transform = components::Transform::new(10, 20, 32, 64, 0.0, 1.0, 1.0);
+        let mut frame = Frame{
+            filename: "player.png".to_string(),
+            frame: FrameData{ x: 0, y: 0, w: 24, h: 48 },
+            rotated: false,
+            trimmed: true,
+            sprite_source_size: FrameData{ x: 4, y: 8, w: 24, h: 48 },
+            source_size: Size{ w: 32, h: 64 },
+            duration: None,
         };
 
-        self.projection.proj = (*camera).0.into();
+        // the trimmed frame sits where it was cut from, 4 in from the left and 8 down from the top
+        let quad = get_quad(&transform, Some(&frame), [128.0, 128.0], [1.0; 4]);
+        let positions: Vec<[f32; 2]> = quad.iter().map(|vertex| vertex.pos).collect();
+        assert_eq!(positions, vec![[14.0, 28.0], [38.0, 28.0], [38.0, 76.0], [14.0, 76.0]]);
+
+        // packed on its side, the frame takes up 48 across and 24 down the sheet
+        frame.rotated = true;
+        let quad = get_quad(&transform, Some(&frame), [96.0, 96.0], [1.0; 4]);
+        let uvs: Vec<[f32; 2]> = quad.iter().map(|vertex| vertex.uv).collect();
+        assert_eq!(uvs, vec![[0.0, 0.0], [0.0, 0.25], [0.5, 0.25], [0.5, 0.0]]);
+    }
+
+    #[test]
+    fn test_get_quad_rotated_scaled_and_flipped() {
+        let mut transform = components::Transform::new(0, 0, 32, 32, ::std::f32::consts::PI / 2.0, 2.0, 1.0);
+        transform.flip_x = true;
+
+        let quad = get_quad(&transform, None, [128.0, 128.0], [1.0; 4]);
+        // a quarter turn around the centre, after doubling the width
+        let positions: Vec<[i32; 2]> = quad.iter().map(|vertex| [vertex.pos[0].round() as i32, vertex.pos[1].round() as i32]).collect();
+        assert_eq!(positions, vec![[32, -16], [32, 48], [0, 48], [0, -16]]);
+        assert_eq!(quad[0].uv, [1.0, 1.0]);
+        assert_eq!(quad[1].uv, [0.0, 1.0]);
+
+        transform.pivot.x = 0.0;
+        transform.pivot.y = 0.0;
+        transform.rotation = 0.0;
+        let quad = get_quad(&transform, None, [128.0, 128.0], [1.0; 4]);
+        assert_eq!(quad[2].pos, [64.0, 32.0]);
+    }
 
-        encoder.update_constant_buffer(&params.projection_cb, &self.projection);
-        encoder.draw(&slice, &self.pso, &params);
+    #[test]
+    fn test_get_index_data() {
+        assert_eq!(get_index_data(2), vec![0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4]);
     }
 }
@@ -1,14 +1,22 @@
-use cgmath::{ortho, Matrix4};
+use cgmath::{ortho, Matrix4, SquareMatrix, Vector2, Vector4};
 use gfx;
 
+mod backdrop;
 mod tiled;
 mod basic;
+mod scene;
+mod software;
+mod tilemap;
+pub mod viewport;
 
 pub type ColorFormat = gfx::format::Rgba8;
 pub type DepthFormat = gfx::format::DepthStencil;
 
+pub use self::backdrop::*;
 pub use self::basic::*;
-pub use self::tiled::*;
+pub use self::scene::*;
+pub use self::software::*;
+pub use self::tilemap::*;
 
 pub fn get_ortho() -> Matrix4<f32> {
     let dim = get_dimensions();
@@ -21,4 +29,17 @@ pub fn get_ortho() -> Matrix4<f32> {
 
 pub fn get_dimensions() -> [f32; 2] {
     [960.0, 640.0]
-}
\ No newline at end of file
+}
+
+/**
+ * Returns the world position of the bottom left corner of the area a projection shows, and its size.
+ */
+pub fn get_view_bounds(proj: &Matrix4<f32>) -> (Vector2<f32>, Vector2<f32>) {
+    let inverse = proj.invert().unwrap_or_else(Matrix4::identity);
+    let bottom_left = inverse * Vector4::new(-1.0, -1.0, 0.0, 1.0);
+    let top_right = inverse * Vector4::new(1.0, 1.0, 0.0, 1.0);
+    (
+        Vector2::new(bottom_left.x, bottom_left.y),
+        Vector2::new(top_right.x - bottom_left.x, top_right.y - bottom_left.y),
+    )
+}
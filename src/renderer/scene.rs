@@ -0,0 +1,178 @@
+extern crate cgmath;
+extern crate specs;
+
+use cgmath::Matrix4;
+use specs::{Join, World};
+
+use components::{AnimationSheet, Camera, Color, ParticleEmitter, Rect, ScreenSpace, Sprite, Text, Transform};
+use font::BitmapFont;
+use spritesheet::Spritesheet;
+use ui::{Ui, UiElement};
+use super::basic::{get_quad, get_text_quads, Vertex};
+
+pub const CLEAR_COLOR: [f32; 4] = [16.0 / 256.0, 14.0 / 256.0, 22.0 / 256.0, 1.0];
+// placeholder colour for frames missing from the spritesheet
+const MISSING_FRAME_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+
+/**
+ * Backend the frame is drawn with. Quads are queued with add_quad and drawn in order on flush,
+ * so the same drawing code can target the gpu or an image in tests.
+ */
+pub trait Renderer {
+    type Texture;
+
+    fn clear(&mut self, color: [f32; 4]);
+
+    /**
+     * Queues a quad for drawing. Without a texture it is filled with its vertex colour.
+     */
+    fn add_quad(&mut self, texture: Option<&Self::Texture>, quad: [Vertex; 4]);
+
+    /**
+     * Draws the queued quads in the order they were added with the projection, and clears the queue.
+     */
+    fn flush(&mut self, proj: Matrix4<f32>);
+
+    /**
+     * Draws the map's image layers and the tile layers behind entities, or the ones in front of them.
+     */
+    fn draw_map_layers(&mut self, proj: Matrix4<f32>, foreground: bool);
+
+    /**
+     * Queues a spritesheet frame. Frames missing from the sheet are drawn as a magenta box instead.
+     */
+    fn add_frame(&mut self, texture: &Self::Texture, spritesheet: &Spritesheet, transform: &Transform, frame_name: &str) {
+        self.add_tinted_frame(texture, spritesheet, transform, frame_name, [1.0; 4]);
+    }
+
+    /**
+     * Queues a spritesheet frame, multiplied by the colour.
+     */
+    fn add_tinted_frame(&mut self, texture: &Self::Texture, spritesheet: &Spritesheet, transform: &Transform, frame_name: &str, color: [f32; 4]) {
+        match spritesheet.get_frame(frame_name) {
+            Ok(frame) => self.add_quad(Some(texture), get_quad(transform, Some(frame), spritesheet.get_size(), color)),
+            Err(_) => self.add_quad(None, get_quad(transform, None, spritesheet.get_size(), MISSING_FRAME_COLOR)),
+        }
+    }
+
+    /**
+     * Queues the glyphs of a text, textured with the font's atlas.
+     */
+    fn add_text(&mut self, texture: &Self::Texture, font: &BitmapFont, transform: &Transform, text: &Text, color: [f32; 4]) {
+        for quad in get_text_quads(transform, font, text, color) {
+            self.add_quad(Some(texture), quad);
+        }
+    }
+}
+
+pub struct SceneAssets<'a, T: 'a> {
+    pub spritesheet: &'a Spritesheet,
+    pub sprite_texture: &'a T,
+    pub font: &'a BitmapFont,
+    pub font_texture: &'a T,
+}
+
+// sprites are drawn alpha of the way between their positions from the last two steps
+fn add_sprites<T: Renderer>(renderer: &mut T, world: &World, assets: &SceneAssets<T::Texture>, alpha: f32) {
+    let sprites = world.read::<Sprite>();
+    let transforms = world.read::<Transform>();
+    let animation_sheets = world.read::<AnimationSheet>();
+
+    let mut frames: Vec<(Transform, &String)> = (&sprites, &transforms).join()
+        .filter(|&(sprite, _)| sprite.visible)
+        .map(|(sprite, transform)| (transform.interpolate(alpha), &sprite.frame_name))
+        .chain((&animation_sheets, &transforms).join().map(|(animation_sheet, transform)| (transform.interpolate(alpha), animation_sheet.get_current_frame())))
+        .collect();
+    // stable, so sprites still draw before animations on the same z order
+    frames.sort_by_key(|(transform, _)| transform.z_order);
+
+    for (transform, frame_name) in frames {
+        renderer.add_frame(assets.sprite_texture, assets.spritesheet, &transform, frame_name);
+    }
+
+    // particles go over the sprites, centred on their position
+    let emitters = world.read::<ParticleEmitter>();
+    for emitter in emitters.join() {
+        for particle in emitter.particles.iter() {
+            let x = particle.pos.x as i32 - emitter.size.x as i32 / 2;
+            let y = particle.pos.y as i32 - emitter.size.y as i32 / 2;
+            let transform = Transform::new(x, y, emitter.size.x, emitter.size.y, 0.0, 1.0, 1.0);
+            renderer.add_tinted_frame(assets.sprite_texture, assets.spritesheet, &transform, &emitter.frame_name, emitter.get_color(particle));
+        }
+    }
+}
+
+// rects and then texts, either those in the world or the screen space ones
+fn add_rects_and_texts<T: Renderer>(renderer: &mut T, world: &World, assets: &SceneAssets<T::Texture>, screen_space: bool) {
+    let entities = world.entities();
+    let transforms = world.read::<Transform>();
+    let colors = world.read::<Color>();
+    let rects = world.read::<Rect>();
+    let texts = world.read::<Text>();
+    let screen_spaces = world.read::<ScreenSpace>();
+
+    let mut colored_rects: Vec<(&Transform, &Color)> = (&*entities, &colors, &transforms, &rects).join()
+        .filter(|&(entity, _, _, _)| screen_spaces.get(entity).is_some() == screen_space)
+        .map(|(_, color, transform, _)| (transform, color))
+        .collect();
+    colored_rects.sort_by_key(|&(transform, _)| transform.z_order);
+
+    for (transform, color) in colored_rects {
+        renderer.add_quad(None, get_quad(transform, None, assets.spritesheet.get_size(), color.0));
+    }
+
+    let mut colored_texts: Vec<(&Transform, &Text, [f32; 4])> = (&*entities, &transforms, &texts).join()
+        .filter(|&(entity, _, _)| screen_spaces.get(entity).is_some() == screen_space)
+        .map(|(entity, transform, text)| (transform, text, colors.get(entity).map_or([1.0; 4], |color| color.0)))
+        .collect();
+    colored_texts.sort_by_key(|&(transform, _, _)| transform.z_order);
+
+    for (transform, text, color) in colored_texts {
+        renderer.add_text(assets.font_texture, assets.font, transform, text, color);
+    }
+}
+
+// the immediate mode ui, in the order it was built
+fn add_ui<T: Renderer>(renderer: &mut T, world: &World, assets: &SceneAssets<T::Texture>) {
+    let ui = world.read_resource::<Ui>();
+    for element in ui.elements.iter() {
+        match *element {
+            UiElement::Rect(ref transform, color) => renderer.add_quad(None, get_quad(transform, None, assets.spritesheet.get_size(), color)),
+            UiElement::Icon(ref transform, ref frame_name) => renderer.add_frame(assets.sprite_texture, assets.spritesheet, transform, frame_name),
+            UiElement::Label(ref transform, ref text, color) => renderer.add_text(assets.font_texture, assets.font, transform, text, color),
+        }
+    }
+}
+
+/**
+ * Draws the map, entities and ui for the current state of the world. Alpha is how far the
+ * frame is between the last two logic steps, with 1 drawing the latest step as it is.
+ */
+pub fn draw_frame<T: Renderer>(renderer: &mut T, world: &World, assets: &SceneAssets<T::Texture>, alpha: f32) {
+    renderer.clear(CLEAR_COLOR);
+
+    let camera = world.read_resource::<Camera>().interpolate(alpha);
+    let camera_projection = camera.get_projection();
+
+    renderer.draw_map_layers(camera_projection, false);
+
+    add_sprites(renderer, world, assets, alpha);
+    renderer.flush(camera_projection);
+
+    renderer.draw_map_layers(camera_projection, true);
+
+    add_rects_and_texts(renderer, world, assets, false);
+    renderer.flush(camera_projection);
+
+    // ui stays put while the camera scrolls
+    add_rects_and_texts(renderer, world, assets, true);
+    add_ui(renderer, world, assets);
+    renderer.flush(camera.get_ui_projection());
+
+    // cover whatever the world drew outside the viewport
+    for (pos, size) in camera.get_viewport().get_bars(camera.window_size) {
+        let transform = Transform::new(pos.x as i32, pos.y as i32, size.x.ceil() as u16, size.y.ceil() as u16, 0.0, 1.0, 1.0);
+        renderer.add_quad(None, get_quad(&transform, None, assets.spritesheet.get_size(), [0.0, 0.0, 0.0, 1.0]));
+    }
+    renderer.flush(camera.get_window_projection());
+}
@@ -0,0 +1,306 @@
+//! Renderer drawing into an image on the cpu, so frames can be checked without a gpu.
+//! Textures are sampled with the nearest texel, so output is close to but not exactly the gpu's.
+
+extern crate cgmath;
+extern crate image;
+extern crate tiled;
+
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use cgmath::{Matrix4, Vector4};
+
+use math::chunk;
+use math::chunk::CHUNK_SIZE;
+use renderer;
+use utils::tiled::ImageLayer;
+use super::backdrop::get_backdrop_quad;
+use super::basic::Vertex;
+use super::scene::Renderer;
+use super::tilemap::get_tile_entry;
+
+pub type SoftwareTexture = Rc<image::RgbaImage>;
+
+pub fn load_texture(path: &str) -> image::ImageResult<SoftwareTexture> {
+    Ok(Rc::new(image::open(path)?.to_rgba()))
+}
+
+/**
+ * Map and textures for drawing its layers, in place of MapRenderData.
+ */
+pub struct SoftwareMap {
+    pub tilemap: tiled::Map,
+    pub tiles_texture: SoftwareTexture,
+    pub image_layers: Vec<(ImageLayer, SoftwareTexture)>,
+}
+
+pub struct SoftwareRenderer {
+    pub image: image::RgbaImage,
+    quads: Vec<(Option<SoftwareTexture>, [Vertex; 4])>,
+    map: Option<SoftwareMap>,
+}
+
+// vertex moved to pixel space, y going down from the top of the image
+#[derive(Clone, Copy)]
+struct PixelVertex {
+    x: f32,
+    y: f32,
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+// positions are snapped to a 256th of a pixel, so in f64 this is exact and neighbouring triangles agree on their shared edges
+fn edge(a: &PixelVertex, b: &PixelVertex, x: f32, y: f32) -> f64 {
+    let (ax, ay, bx, by) = (a.x as f64, a.y as f64, b.x as f64, b.y as f64);
+    (bx - ax) * (y as f64 - ay) - (by - ay) * (x as f64 - ax)
+}
+
+fn snap(n: f32) -> f32 {
+    (n * 256.0).round() / 256.0
+}
+
+// pixels exactly on an edge are only filled for top and left edges, so triangles sharing one don't both draw it
+fn is_top_left(a: &PixelVertex, b: &PixelVertex) -> bool {
+    (a.y == b.y && b.x < a.x) || b.y < a.y
+}
+
+fn sample(texture: &image::RgbaImage, uv: [f32; 2]) -> [f32; 4] {
+    let (width, height) = texture.dimensions();
+    // wraps around, like the repeating backdrops
+    let x = ((uv[0] * width as f32).floor() as i64).rem_euclid(width as i64) as u32;
+    let y = ((uv[1] * height as f32).floor() as i64).rem_euclid(height as i64) as u32;
+    let texel = texture.get_pixel(x, y).data;
+    [texel[0] as f32 / 255.0, texel[1] as f32 / 255.0, texel[2] as f32 / 255.0, texel[3] as f32 / 255.0]
+}
+
+fn blend(image: &mut image::RgbaImage, x: u32, y: u32, color: [f32; 4]) {
+    let pixel = image.get_pixel_mut(x, y);
+    let alpha = color[3];
+    for (dest, channel) in pixel.data.iter_mut().zip(color.iter()).take(3) {
+        *dest = ((channel * alpha + *dest as f32 / 255.0 * (1.0 - alpha)) * 255.0).round() as u8;
+    }
+    let dest_alpha = pixel.data[3] as f32 / 255.0;
+    pixel.data[3] = ((alpha + dest_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+}
+
+fn draw_triangle(image: &mut image::RgbaImage, texture: Option<&image::RgbaImage>, v0: PixelVertex, v1: PixelVertex, v2: PixelVertex) {
+    // wind the same way whatever the flips and rotations, so the insides of all edges are positive
+    let (v1, v2) = if edge(&v0, &v1, v2.x, v2.y) < 0.0 { (v2, v1) } else { (v1, v2) };
+    let area = edge(&v0, &v1, v2.x, v2.y);
+    if area == 0.0 {
+        return
+    }
+
+    let (width, height) = image.dimensions();
+    let min_x = v0.x.min(v1.x).min(v2.x).floor().max(0.0) as u32;
+    let min_y = v0.y.min(v1.y).min(v2.y).floor().max(0.0) as u32;
+    let max_x = (v0.x.max(v1.x).max(v2.x).ceil().max(0.0) as u32).min(width);
+    let max_y = (v0.y.max(v1.y).max(v2.y).ceil().max(0.0) as u32).min(height);
+
+    let edges = [(&v1, &v2), (&v2, &v0), (&v0, &v1)];
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            // sample at the pixel's centre
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let mut weights = [0.0; 3];
+            let mut inside = true;
+            for (i, &(a, b)) in edges.iter().enumerate() {
+                let w = edge(a, b, px, py);
+                if w < 0.0 || (w == 0.0 && !is_top_left(a, b)) {
+                    inside = false;
+                    break
+                }
+                weights[i] = (w / area) as f32;
+            }
+            if !inside {
+                continue
+            }
+
+            let interpolate = |get: &dyn Fn(&PixelVertex) -> f32| get(&v0) * weights[0] + get(&v1) * weights[1] + get(&v2) * weights[2];
+            let mut color = [
+                interpolate(&|v| v.color[0]),
+                interpolate(&|v| v.color[1]),
+                interpolate(&|v| v.color[2]),
+                interpolate(&|v| v.color[3]),
+            ];
+            if let Some(texture) = texture {
+                let texel = sample(texture, [interpolate(&|v| v.uv[0]), interpolate(&|v| v.uv[1])]);
+                for (channel, texel_channel) in color.iter_mut().zip(texel.iter()) {
+                    *channel *= texel_channel;
+                }
+            }
+            blend(image, x, y, color);
+        }
+    }
+}
+
+impl SoftwareRenderer {
+    pub fn new(width: u32, height: u32) -> SoftwareRenderer {
+        SoftwareRenderer{
+            image: image::RgbaImage::new(width, height),
+            quads: Vec::new(),
+            map: None,
+        }
+    }
+
+    pub fn set_map(&mut self, map: SoftwareMap) {
+        self.map = Some(map);
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.image.save(path)
+    }
+
+    /**
+     * Queues every tile of a layer, shifted by its parallax for the projection it is drawn with.
+     */
+    pub fn add_tile_layer(&mut self, texture: &SoftwareTexture, tilemap: &tiled::Map, layer: &tiled::Layer, proj: &Matrix4<f32>) {
+        let (view_pos, _) = renderer::get_view_bounds(proj);
+        let offset = view_pos * (1.0 - ::utils::tiled::get_parallax(&layer.properties));
+        let color = [1.0, 1.0, 1.0, layer.opacity];
+        let tileset = &tilemap.tilesets[0];
+        let image_size = [tileset.images[0].width as f32, tileset.images[0].height as f32];
+        let (w, h) = (tilemap.tile_width as f32, tilemap.tile_height as f32);
+
+        // the same chunks and lookup entries the tilemap shader draws from
+        for chunk in chunk::get_chunks(tilemap.width as usize, tilemap.height as usize, CHUNK_SIZE) {
+            for row in chunk.row..(chunk.row + chunk.rows) {
+                for col in chunk.col..(chunk.col + chunk.cols) {
+                    let entry = get_tile_entry(tileset, layer.tiles[row][col]);
+                    if entry[2] == 0.0 {
+                        continue
+                    }
+                    let x = col as f32 * w + offset.x;
+                    let y = (tilemap.height as usize - row - 1) as f32 * h + offset.y;
+                    let (u0, v0) = (entry[0] / image_size[0], entry[1] / image_size[1]);
+                    let (u1, v1) = ((entry[0] + tileset.tile_width as f32) / image_size[0], (entry[1] + tileset.tile_height as f32) / image_size[1]);
+                    self.add_quad(Some(texture), [
+                        Vertex{ pos: [x, y], uv: [u0, v1], color },
+                        Vertex{ pos: [x + w, y], uv: [u1, v1], color },
+                        Vertex{ pos: [x + w, y + h], uv: [u1, v0], color },
+                        Vertex{ pos: [x, y + h], uv: [u0, v0], color },
+                    ]);
+                }
+            }
+        }
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    type Texture = SoftwareTexture;
+
+    fn clear(&mut self, color: [f32; 4]) {
+        let pixel = image::Rgba{ data: [
+            (color[0] * 255.0).round() as u8,
+            (color[1] * 255.0).round() as u8,
+            (color[2] * 255.0).round() as u8,
+            (color[3] * 255.0).round() as u8,
+        ] };
+        for image_pixel in self.image.pixels_mut() {
+            *image_pixel = pixel;
+        }
+    }
+
+    fn add_quad(&mut self, texture: Option<&Self::Texture>, quad: [Vertex; 4]) {
+        self.quads.push((texture.cloned(), quad));
+    }
+
+    fn flush(&mut self, proj: Matrix4<f32>) {
+        let (width, height) = (self.image.width() as f32, self.image.height() as f32);
+        let to_pixels = |vertex: &Vertex| {
+            let ndc = proj * Vector4::new(vertex.pos[0], vertex.pos[1], 0.0, 1.0);
+            PixelVertex{
+                x: snap((ndc.x + 1.0) / 2.0 * width),
+                y: snap((1.0 - ndc.y) / 2.0 * height),
+                uv: vertex.uv,
+                color: vertex.color,
+            }
+        };
+
+        for (texture, quad) in self.quads.drain(..) {
+            let corners: Vec<PixelVertex> = quad.iter().map(&to_pixels).collect();
+            let texture = texture.as_deref();
+            draw_triangle(&mut self.image, texture, corners[0], corners[1], corners[2]);
+            draw_triangle(&mut self.image, texture, corners[2], corners[3], corners[0]);
+        }
+    }
+
+    fn draw_map_layers(&mut self, proj: Matrix4<f32>, foreground: bool) {
+        let map = match self.map.take() {
+            Some(map) => map,
+            None => return,
+        };
+        let map_height = (map.tilemap.height * map.tilemap.tile_height) as f32;
+
+        if !foreground {
+            for (image_layer, texture) in map.image_layers.iter().filter(|(image_layer, _)| image_layer.visible) {
+                let color = [1.0, 1.0, 1.0, image_layer.opacity];
+                let mut quad = [Vertex{ pos: [0.0; 2], uv: [0.0; 2], color }; 4];
+                for (vertex, &(pos, uv)) in quad.iter_mut().zip(get_backdrop_quad(image_layer, map_height, &proj).iter()) {
+                    vertex.pos = pos;
+                    vertex.uv = uv;
+                }
+                self.add_quad(Some(texture), quad);
+            }
+        }
+
+        let (background, foreground_layers) = ::utils::tiled::get_drawn_layers(&map.tilemap);
+        let layers = if foreground { foreground_layers } else { background };
+        for index in layers {
+            self.add_tile_layer(&map.tiles_texture, &map.tilemap, &map.tilemap.layers[index], &proj);
+        }
+        self.flush(proj);
+
+        self.map = Some(map);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use cgmath::ortho;
+
+    const GOLDEN_DIR: &str = "tests/golden";
+
+    // compares against the golden image, or writes it when UPDATE_GOLDEN is set
+    fn assert_golden(name: &str, image: &image::RgbaImage) {
+        let path = format!("{}/{}.png", GOLDEN_DIR, name);
+        if env::var("UPDATE_GOLDEN").is_ok() {
+            image.save(&path).unwrap();
+            return
+        }
+
+        let golden = image::open(&path).unwrap_or_else(|err| panic!("{}: {}, run with UPDATE_GOLDEN=1 to create it", path, err)).to_rgba();
+        assert_eq!(golden.dimensions(), image.dimensions());
+        let different = golden.pixels().zip(image.pixels())
+            .filter(|&(a, b)| a.data.iter().zip(b.data.iter()).any(|(a, b)| (*a as i32 - *b as i32).abs() > 1))
+            .count();
+        if different > 0 {
+            let actual_path = format!("{}/{}.actual.png", GOLDEN_DIR, name);
+            image.save(&actual_path).unwrap();
+            panic!("{} pixels differ from {}, see {}", different, path, actual_path);
+        }
+    }
+
+    #[test]
+    fn test_shared_edges_drawn_once() {
+        let mut renderer = SoftwareRenderer::new(16, 16);
+        renderer.clear([0.0, 0.0, 0.0, 1.0]);
+        let color = [1.0, 1.0, 1.0, 0.5];
+        let corner = |x: f32, y: f32| Vertex{ pos: [x, y], uv: [0.0, 0.0], color };
+        renderer.add_quad(None, [corner(0.0, 0.0), corner(16.0, 0.0), corner(16.0, 16.0), corner(0.0, 16.0)]);
+        renderer.flush(ortho(0.0, 16.0, 0.0, 16.0, 0.0, 1.0));
+
+        // blending twice along the diagonal would make it brighter
+        assert!(renderer.image.pixels().all(|pixel| pixel.data == [128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn test_draw_frame() {
+        // the tile map, sprites and hud of the starting frame
+        let renderer = ::draw_software_frame().unwrap();
+        assert_golden("frame", &renderer.image);
+    }
+}
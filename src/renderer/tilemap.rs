@@ -0,0 +1,296 @@
+extern crate gfx;
+extern crate cgmath;
+extern crate tiled;
+
+use cgmath::{Matrix4, SquareMatrix, Vector3};
+
+use renderer;
+use renderer::{WindowTargets};
+
+use math::chunk;
+use math::chunk::{Chunk, CHUNK_SIZE};
+use utils;
+
+// length of the lookup array in tilemap.glslf, one entry for each tile of a full chunk
+pub const TILEMAP_BUF_LENGTH: usize = CHUNK_SIZE * CHUNK_SIZE;
+
+/**
+ * Lookup entry for a tile: the top left of its image in the tilesheet in pixels, and a z of 1.
+ * Empty cells, and cells outside the tileset, are all zeros.
+ */
+pub fn get_tile_entry(tileset: &tiled::Tileset, cell: u32) -> [f32; 4] {
+    if cell < tileset.first_gid {
+        return [0.0; 4]
+    }
+
+    let image = &tileset.images[0];
+    let columns = ((image.width as u32 + tileset.spacing).saturating_sub(tileset.margin * 2) / (tileset.tile_width + tileset.spacing)).max(1);
+    let id = cell - tileset.first_gid;
+    [
+        (tileset.margin + (id % columns) * (tileset.tile_width + tileset.spacing)) as f32,
+        (tileset.margin + (id / columns) * (tileset.tile_height + tileset.spacing)) as f32,
+        1.0,
+        0.0,
+    ]
+}
+
+/**
+ * Index into the chunk's lookup buffer of a tile, with rows counted up from the bottom of the chunk.
+ */
+pub fn get_lookup_index(chunk: &Chunk, x: usize, y: usize) -> usize {
+    (chunk.row + chunk.rows - 1 - y) * chunk.cols + x - chunk.col
+}
+
+/**
+ * Packs the tiles of the layer within the chunk into the lookup buffer the tilemap shader reads.
+ */
+pub fn get_tile_lookup(tilemap: &tiled::Map, layer: &tiled::Layer, chunk: &Chunk) -> Vec<[f32; 4]> {
+    let tileset = &tilemap.tilesets[0]; // working under the assumption i will only use one tileset
+    let mut lookup = vec![[0.0; 4]; chunk.cols * chunk.rows];
+    for row in chunk.row..(chunk.row + chunk.rows) {
+        for col in chunk.col..(chunk.col + chunk.cols) {
+            lookup[get_lookup_index(chunk, col, row)] = get_tile_entry(tileset, layer.tiles[row][col]);
+        }
+    }
+    lookup
+}
+
+gfx_defines!{
+    vertex TileVertex {
+        pos: [f32; 3] = "a_Pos",
+        buf_pos: [f32; 2] = "a_BufPos",
+    }
+
+    constant TileMapData {
+        data: [f32; 4] = "data",
+    }
+
+    constant TileMapProjection {
+        model: [[f32; 4]; 4] = "u_Model",
+        proj: [[f32; 4]; 4] = "u_Proj",
+    }
+
+    constant TileMapLocals {
+        world_size: [f32; 4] = "u_WorldSize",
+        tilesheet_size: [f32; 4] = "u_TilesheetSize",
+        opacity: f32 = "u_Opacity",
+    }
+
+    pipeline tilemap_pipe {
+        vbuf: gfx::VertexBuffer<TileVertex> = (),
+        projection_cb: gfx::ConstantBuffer<TileMapProjection> = "b_VsLocals",
+        tilemap: gfx::ConstantBuffer<TileMapData> = "b_TileMap",
+        tilemap_cb: gfx::ConstantBuffer<TileMapLocals> = "b_PsLocals",
+        tilesheet: gfx::TextureSampler<[f32; 4]> = "t_TileSheet",
+        out: gfx::BlendTarget<renderer::ColorFormat> = ("Target0", gfx::state::ColorMask::all(), gfx::preset::blend::ALPHA),
+    }
+}
+
+/**
+ * Draws a tile layer as one quad per chunk, with the tiles looked up in a buffer by the shader.
+ * Changing a tile only updates its entry in the buffer.
+ */
+pub struct TileMapRenderer<R: gfx::Resources> {
+    pso: gfx::PipelineState<R, tilemap_pipe::Meta>,
+    projection: TileMapProjection,
+    projection_cb: gfx::handle::Buffer<R, TileMapProjection>,
+    chunks: Vec<Chunk>,
+    chunk_meshes: Vec<(tilemap_pipe::Data<R>, gfx::Slice<R>)>,
+    // whether each chunk has any tiles to draw
+    filled: Vec<bool>,
+    // chunk index, offset and entries still to be uploaded to the chunk's lookup buffer
+    pending: Vec<(usize, usize, Vec<TileMapData>)>,
+    map_rows: usize,
+    tile_size: [f32; 2],
+    parallax: f32,
+}
+
+impl <R>TileMapRenderer<R>
+    where R: gfx::Resources
+{
+    pub fn new<F>(factory: &mut F, tilemap: &tiled::Map, layer: &tiled::Layer, tiles_texture: &gfx::handle::ShaderResourceView<R, [f32; 4]>, target: &WindowTargets<R>) -> TileMapRenderer<R>
+        where F: gfx::Factory<R>
+    {
+        use gfx::traits::FactoryExt;
+
+        let pso = factory.create_pipeline_simple(
+            include_bytes!("shaders/tilemap.glslv"),
+            include_bytes!("shaders/tilemap.glslf"),
+            tilemap_pipe::new()
+        ).unwrap();
+
+        let chunks = chunk::get_chunks(tilemap.width as usize, tilemap.height as usize, CHUNK_SIZE);
+        let map_rows = tilemap.height as usize;
+        let tile_size = [tilemap.tile_width as f32, tilemap.tile_height as f32];
+        let tileset = &tilemap.tilesets[0];
+        let image = &tileset.images[0];
+        let projection_cb = factory.create_constant_buffer(1);
+        let sampler = factory.create_sampler_linear();
+
+        let mut chunk_meshes = Vec::with_capacity(chunks.len());
+        let mut filled = Vec::with_capacity(chunks.len());
+        let mut pending = Vec::with_capacity(chunks.len());
+        for (i, chunk) in chunks.iter().enumerate() {
+            let (pos, size) = chunk.get_world_bounds(map_rows, tile_size);
+            let (cols, rows) = (chunk.cols as f32, chunk.rows as f32);
+            let vertex_data = [
+                TileVertex{ pos: [pos[0], pos[1], 0.0], buf_pos: [0.0, 0.0] },
+                TileVertex{ pos: [pos[0] + size[0], pos[1], 0.0], buf_pos: [cols, 0.0] },
+                TileVertex{ pos: [pos[0] + size[0], pos[1] + size[1], 0.0], buf_pos: [cols, rows] },
+                TileVertex{ pos: [pos[0], pos[1] + size[1], 0.0], buf_pos: [0.0, rows] },
+            ];
+            let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&vertex_data, &[0u16, 1, 2, 2, 3, 0][..]);
+
+            let locals = TileMapLocals{
+                world_size: [cols, rows, 0.0, 0.0],
+                tilesheet_size: [image.width as f32, image.height as f32, tileset.tile_width as f32, tileset.tile_height as f32],
+                opacity: layer.opacity,
+            };
+            let params = tilemap_pipe::Data{
+                vbuf,
+                projection_cb: projection_cb.clone(),
+                tilemap: factory.create_constant_buffer(TILEMAP_BUF_LENGTH),
+                tilemap_cb: factory.create_buffer_immutable(&[locals], gfx::buffer::Role::Constant, gfx::Bind::empty()).unwrap(),
+                tilesheet: (tiles_texture.clone(), sampler.clone()),
+                out: target.color.clone(),
+            };
+            chunk_meshes.push((params, slice));
+
+            let lookup = get_tile_lookup(tilemap, layer, chunk);
+            filled.push(lookup.iter().any(|entry| entry[2] != 0.0));
+            pending.push((i, 0, lookup.into_iter().map(|data| TileMapData{ data }).collect()));
+        }
+
+        TileMapRenderer{
+            pso,
+            projection: TileMapProjection{
+                model: Matrix4::identity().into(),
+                proj: renderer::get_ortho().into(),
+            },
+            projection_cb,
+            chunks,
+            chunk_meshes,
+            filled,
+            pending,
+            map_rows,
+            tile_size,
+            parallax: utils::tiled::get_parallax(&layer.properties),
+        }
+    }
+
+    /**
+     * Queues the tile's new lookup entry for upload, call after changing it in the layer.
+     */
+    pub fn update_tile(&mut self, tilemap: &tiled::Map, layer: &tiled::Layer, x: usize, y: usize) {
+        let index = chunk::get_chunk_index(x, y, tilemap.width as usize, CHUNK_SIZE);
+        let entry = get_tile_entry(&tilemap.tilesets[0], layer.tiles[y][x]);
+        if entry[2] != 0.0 {
+            self.filled[index] = true;
+        }
+        self.pending.push((index, get_lookup_index(&self.chunks[index], x, y), vec![TileMapData{ data: entry }]));
+    }
+
+    pub fn set_target(&mut self, target: &WindowTargets<R>) {
+        for (params, _) in self.chunk_meshes.iter_mut() {
+            params.out = target.color.clone();
+        }
+    }
+
+    pub fn render<C>(&mut self,
+        encoder: &mut gfx::Encoder<R, C>,
+        proj: Matrix4<f32>)
+        where R: gfx::Resources, C: gfx::CommandBuffer<R>
+    {
+        self.projection.proj = proj.into();
+
+        // shift the layer along with the camera, so it scrolls at the parallax fraction of its speed
+        let (view_pos, view_size) = renderer::get_view_bounds(&proj);
+        let offset = view_pos * (1.0 - self.parallax);
+        self.projection.model = Matrix4::from_translation(Vector3::new(offset.x, offset.y, 0.0)).into();
+
+        encoder.update_constant_buffer(&self.projection_cb, &self.projection);
+
+        for (index, offset, entries) in self.pending.drain(..) {
+            encoder.update_buffer(&self.chunk_meshes[index].0.tilemap, &entries, offset).unwrap();
+        }
+
+        // chunks are positioned in the layer's space, which is shifted by the parallax offset
+        let layer_view_pos = view_pos - offset;
+        for ((chunk, (params, slice)), filled) in self.chunks.iter().zip(self.chunk_meshes.iter()).zip(self.filled.iter()) {
+            let (pos, size) = chunk.get_world_bounds(self.map_rows, self.tile_size);
+            if *filled && chunk::intersects(pos, size, layer_view_pos.into(), view_size.into()) {
+                encoder.draw(slice, &self.pso, params);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiled::{Image, Layer, Map, Properties, Tileset};
+
+    fn get_map(tiles: Vec<Vec<u32>>) -> Map {
+        let tileset = Tileset{
+            first_gid: 1,
+            name: "tiles".to_string(),
+            tile_width: 16,
+            tile_height: 16,
+            spacing: 2,
+            margin: 1,
+            images: vec![Image{ source: "tiles.png".to_string(), width: 72, height: 72, transparent_colour: None }],
+            tiles: Vec::new(),
+        };
+        Map{
+            version: "1.0".to_string(),
+            orientation: tiled::Orientation::Orthogonal,
+            width: tiles[0].len() as u32,
+            height: tiles.len() as u32,
+            tile_width: 16,
+            tile_height: 16,
+            tilesets: vec![tileset],
+            layers: vec![Layer{ name: "ground".to_string(), opacity: 1.0, visible: true, tiles, properties: Properties::new() }],
+            object_groups: Vec::new(),
+            properties: Properties::new(),
+            background_colour: None,
+        }
+    }
+
+    #[test]
+    fn test_get_tile_entry() {
+        let map = get_map(vec![vec![0]]);
+        let tileset = &map.tilesets[0];
+        assert_eq!(get_tile_entry(tileset, 0), [0.0; 4]);
+        assert_eq!(get_tile_entry(tileset, 1), [1.0, 1.0, 1.0, 0.0]);
+        // four columns of 16 pixel tiles, 2 apart after the 1 pixel margin
+        assert_eq!(get_tile_entry(tileset, 4), [55.0, 1.0, 1.0, 0.0]);
+        assert_eq!(get_tile_entry(tileset, 6), [19.0, 19.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_get_tile_lookup() {
+        let map = get_map(vec![
+            vec![1, 0, 0],
+            vec![0, 0, 2],
+        ]);
+        let whole_map = Chunk{ col: 0, row: 0, cols: 3, rows: 2 };
+        let lookup = get_tile_lookup(&map, &map.layers[0], &whole_map);
+        assert_eq!(lookup.len(), 6);
+        // the bottom row comes first
+        assert_eq!(lookup[2], [19.0, 1.0, 1.0, 0.0]);
+        assert_eq!(lookup[3], [1.0, 1.0, 1.0, 0.0]);
+        assert_eq!(lookup.iter().filter(|entry| entry[2] != 0.0).count(), 2);
+
+        // offset into the chunk the tiles are in
+        let chunk = Chunk{ col: 2, row: 1, cols: 1, rows: 1 };
+        assert_eq!(get_lookup_index(&chunk, 2, 1), 0);
+        assert_eq!(get_tile_lookup(&map, &map.layers[0], &chunk), vec![[19.0, 1.0, 1.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_shader_buffer_length() {
+        // the shader can't see the constant, so they're kept in step by hand
+        let shader = include_str!("shaders/tilemap.glslf");
+        assert!(shader.contains(&format!("const int TILEMAP_BUF_LENGTH = {};", TILEMAP_BUF_LENGTH)));
+    }
+}
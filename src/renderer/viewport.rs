@@ -0,0 +1,120 @@
+//! Fits the game's design resolution into the window, following a scaling policy.
+
+use cgmath::Vector2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScalingPolicy {
+    // scales by whole numbers only so pixels stay square, with bars around the remaining space
+    PixelPerfect,
+    // scales as large as fits while keeping the aspect ratio, with bars on two sides
+    Letterbox,
+    // fills the window, showing more of the world instead of adding bars
+    Expand,
+}
+
+impl ScalingPolicy {
+    pub fn from_name(name: &str) -> Option<ScalingPolicy> {
+        match name {
+            "pixel" => Some(ScalingPolicy::PixelPerfect),
+            "letterbox" => Some(ScalingPolicy::Letterbox),
+            "expand" => Some(ScalingPolicy::Expand),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Area of the window the game is drawn to.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    // bottom left corner in window pixels, y going up
+    pub pos: Vector2<f32>,
+    pub size: Vector2<f32>,
+    // window pixels per design pixel
+    pub scale: f32,
+}
+
+impl Viewport {
+    /**
+     * Returns the bars around the viewport that the game doesn't draw to, as bottom left positions and sizes.
+     */
+    pub fn get_bars(&self, window_size: Vector2<f32>) -> Vec<(Vector2<f32>, Vector2<f32>)> {
+        let mut bars = Vec::new();
+        let right = self.pos.x + self.size.x;
+        let top = self.pos.y + self.size.y;
+        if self.pos.x > 0.0 {
+            bars.push((Vector2::new(0.0, 0.0), Vector2::new(self.pos.x, window_size.y)));
+        }
+        if right < window_size.x {
+            bars.push((Vector2::new(right, 0.0), Vector2::new(window_size.x - right, window_size.y)));
+        }
+        if self.pos.y > 0.0 {
+            bars.push((Vector2::new(self.pos.x, 0.0), Vector2::new(self.size.x, self.pos.y)));
+        }
+        if top < window_size.y {
+            bars.push((Vector2::new(self.pos.x, top), Vector2::new(self.size.x, window_size.y - top)));
+        }
+        bars
+    }
+}
+
+pub fn get_viewport(window_size: Vector2<f32>, design_size: Vector2<f32>, policy: ScalingPolicy) -> Viewport {
+    let fit_scale = (window_size.x / design_size.x).min(window_size.y / design_size.y);
+    let scale = match policy {
+        // windows smaller than the design size fall back to shrinking like letterbox
+        ScalingPolicy::PixelPerfect if fit_scale >= 1.0 => fit_scale.floor(),
+        _ => fit_scale,
+    };
+
+    if policy == ScalingPolicy::Expand {
+        return Viewport{ pos: Vector2::new(0.0, 0.0), size: window_size, scale };
+    }
+
+    let size = design_size * scale;
+    Viewport{
+        pos: Vector2::new(((window_size.x - size.x) / 2.0).floor(), ((window_size.y - size.y) / 2.0).floor()),
+        size,
+        scale,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_viewport() {
+        let design_size = Vector2::new(960.0, 640.0);
+        let window_size = Vector2::new(2000.0, 1400.0);
+
+        let viewport = get_viewport(window_size, design_size, ScalingPolicy::PixelPerfect);
+        assert_eq!(viewport, Viewport{ pos: Vector2::new(40.0, 60.0), size: Vector2::new(1920.0, 1280.0), scale: 2.0 });
+
+        let viewport = get_viewport(window_size, design_size, ScalingPolicy::Letterbox);
+        assert_eq!(viewport.scale, 2000.0 / 960.0);
+        assert_eq!(viewport.pos.x, 0.0);
+        assert_eq!(viewport.pos.y, ((1400.0 - 640.0 * viewport.scale) / 2.0f32).floor());
+
+        let viewport = get_viewport(window_size, design_size, ScalingPolicy::Expand);
+        assert_eq!(viewport.pos, Vector2::new(0.0, 0.0));
+        assert_eq!(viewport.size, window_size);
+
+        // too small for a whole number scale
+        let viewport = get_viewport(Vector2::new(480.0, 480.0), design_size, ScalingPolicy::PixelPerfect);
+        assert_eq!(viewport.scale, 0.5);
+    }
+
+    #[test]
+    fn test_get_bars() {
+        let window_size = Vector2::new(2000.0, 1400.0);
+        let viewport = get_viewport(window_size, Vector2::new(960.0, 640.0), ScalingPolicy::PixelPerfect);
+        let bars = viewport.get_bars(window_size);
+        assert_eq!(bars.len(), 4);
+        let area: f32 = bars.iter().map(|&(_, size)| size.x * size.y).sum();
+        assert_eq!(area, 2000.0 * 1400.0 - 1920.0 * 1280.0);
+
+        let viewport = get_viewport(window_size, Vector2::new(960.0, 640.0), ScalingPolicy::Expand);
+        assert!(viewport.get_bars(window_size).is_empty());
+    }
+}
@@ -1,4 +1,9 @@
 extern crate serde;
+extern crate serde_json;
+
+use std::collections::HashMap;
+use std::fmt;
+use self::serde::de::{Deserializer, MapAccess, SeqAccess, Visitor};
 
 #[derive(Serialize, Deserialize)]
 pub struct FrameData {
@@ -17,21 +22,273 @@ pub struct Size {
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Frame {
+    // the key instead in hash exports
+    #[serde(default)]
     pub filename: String,
     pub frame: FrameData,
     pub rotated: bool,
     pub trimmed: bool,
     pub sprite_source_size: FrameData,
     pub source_size: Size,
+    // milliseconds, only in aseprite exports
+    pub duration: Option<u32>,
+}
+
+// aseprite's default, for frames without a duration
+const DEFAULT_FRAME_DURATION: u32 = 100;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    #[default]
+    Forward,
+    Reverse,
+    Pingpong,
+    PingpongReverse,
+}
+
+/**
+ * A named range of frames from an aseprite export, inclusive of both ends.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct FrameTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    #[serde(default)]
+    pub direction: Direction,
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Meta {
     pub size: Size,
+    #[serde(default)]
+    pub frame_tags: Vec<FrameTag>,
+}
+
+/**
+ * Animation built from a frame tag, with how long each of its frames shows for in seconds.
+ */
+#[derive(Debug, PartialEq)]
+pub struct TaggedAnimation {
+    pub name: String,
+    pub frames: Vec<String>,
+    pub frame_times: Vec<f32>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SpritesheetError {
+    MissingFrame(String),
+}
+
+impl fmt::Display for SpritesheetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SpritesheetError::MissingFrame(ref name) => write!(f, "frame \"{}\" is not in the spritesheet", name),
+        }
+    }
+}
+
+struct FramesVisitor;
+
+impl<'de> Visitor<'de> for FramesVisitor {
+    type Value = Vec<Frame>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of frames, or an object of frames keyed by filename")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Vec<Frame>, A::Error> where A: SeqAccess<'de> {
+        let mut frames = Vec::new();
+        while let Some(frame) = seq.next_element()? {
+            frames.push(frame);
+        }
+        Ok(frames)
+    }
+
+    // read in the file's order rather than into a map, as frame tags index frames by it
+    fn visit_map<A>(self, mut map: A) -> Result<Vec<Frame>, A::Error> where A: MapAccess<'de> {
+        let mut frames = Vec::new();
+        while let Some((filename, mut frame)) = map.next_entry::<String, Frame>()? {
+            frame.filename = filename;
+            frames.push(frame);
+        }
+        Ok(frames)
+    }
+}
+
+// accepts both of aseprite's layouts, the json-array export and its default hash export
+fn deserialize_frames<'de, D>(deserializer: D) -> Result<Vec<Frame>, D::Error> where D: Deserializer<'de> {
+    deserializer.deserialize_any(FramesVisitor)
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Spritesheet {
+    #[serde(deserialize_with = "deserialize_frames")]
     pub frames: Vec<Frame>,
-    pub meta: Meta
-}
\ No newline at end of file
+    pub meta: Meta,
+    // frame name to its index in frames, built on load
+    #[serde(skip_serializing, skip_deserializing)]
+    frame_index: HashMap<String, usize>,
+}
+
+impl Spritesheet {
+    pub fn from_json(data: &str) -> serde_json::Result<Spritesheet> {
+        let mut spritesheet: Spritesheet = serde_json::from_str(data)?;
+        spritesheet.frame_index = spritesheet.frames.iter()
+            .enumerate()
+            .map(|(i, frame)| (frame.filename.clone(), i))
+            .collect();
+        Ok(spritesheet)
+    }
+
+    pub fn get_frame(&self, name: &str) -> Result<&Frame, SpritesheetError> {
+        self.frame_index.get(name)
+            .map(|i| &self.frames[*i])
+            .ok_or_else(|| SpritesheetError::MissingFrame(name.to_string()))
+    }
+
+    pub fn get_size(&self) -> [f32; 2] {
+        [self.meta.size.w as f32, self.meta.size.h as f32]
+    }
+
+    /**
+     * Returns an error for each of the names that isn't a frame in the sheet.
+     */
+    pub fn find_missing_frames<'a, I>(&self, names: I) -> Vec<SpritesheetError>
+        where I: Iterator<Item=&'a String>
+    {
+        let mut missing: Vec<SpritesheetError> = Vec::new();
+        for name in names {
+            if let Err(err) = self.get_frame(name) {
+                if !missing.contains(&err) {
+                    missing.push(err);
+                }
+            }
+        }
+        missing
+    }
+
+    /**
+     * Builds an animation for each frame tag, in the order of its direction. Ping pong animations
+     * don't repeat their end frames, so they loop smoothly. Tags starting past their end or the
+     * last frame are skipped, as they have no frames.
+     */
+    pub fn get_tagged_animations(&self) -> Vec<TaggedAnimation> {
+        self.meta.frame_tags.iter().filter_map(|frame_tag| {
+            let to = frame_tag.to.min(self.frames.len().saturating_sub(1));
+            if self.frames.is_empty() || frame_tag.from > to {
+                return None
+            }
+            let forward: Vec<usize> = (frame_tag.from..(to + 1)).collect();
+            let reverse: Vec<usize> = forward.iter().rev().cloned().collect();
+            let indexes: Vec<usize> = match frame_tag.direction {
+                Direction::Forward => forward,
+                Direction::Reverse => reverse,
+                Direction::Pingpong => forward.iter().chain(reverse.iter().skip(1).take(forward.len().saturating_sub(2))).cloned().collect(),
+                Direction::PingpongReverse => reverse.iter().chain(forward.iter().skip(1).take(forward.len().saturating_sub(2))).cloned().collect(),
+            };
+
+            Some(TaggedAnimation{
+                name: frame_tag.name.clone(),
+                frames: indexes.iter().map(|i| self.frames[*i].filename.clone()).collect(),
+                frame_times: indexes.iter().map(|i| self.frames[*i].duration.unwrap_or(DEFAULT_FRAME_DURATION) as f32 / 1000.0).collect(),
+            })
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_frame() {
+        let spritesheet = Spritesheet::from_json(r#"{
+            "frames": [{
+                "filename": "player.png",
+                "frame": {"x":2,"y":2,"w":32,"h":64},
+                "rotated": false,
+                "trimmed": false,
+                "spriteSourceSize": {"x":0,"y":0,"w":32,"h":64},
+                "sourceSize": {"w":32,"h":64}
+            }],
+            "meta": {"size": {"w":128,"h":128}}
+        }"#).unwrap();
+
+        assert_eq!(spritesheet.get_frame("player.png").unwrap().frame.h, 64);
+        assert_eq!(spritesheet.get_frame("playr.png").err(), Some(SpritesheetError::MissingFrame("playr.png".to_string())));
+
+        let names = ["player.png".to_string(), "enemy.png".to_string(), "enemy.png".to_string()];
+        assert_eq!(spritesheet.find_missing_frames(names.iter()), vec![SpritesheetError::MissingFrame("enemy.png".to_string())]);
+    }
+
+    #[test]
+    fn test_get_tagged_animations() {
+        let frame = |name: &str, duration: u32| format!(r#"{{
+            "filename": "{}",
+            "frame": {{"x":0,"y":0,"w":16,"h":16}},
+            "rotated": false,
+            "trimmed": false,
+            "spriteSourceSize": {{"x":0,"y":0,"w":16,"h":16}},
+            "sourceSize": {{"w":16,"h":16}},
+            "duration": {}
+        }}"#, name, duration);
+        let spritesheet = Spritesheet::from_json(&format!(r#"{{
+            "frames": [{}, {}, {}, {}],
+            "meta": {{
+                "size": {{"w":64,"h":16}},
+                "frameTags": [
+                    {{"name": "walk", "from": 0, "to": 2, "direction": "forward"}},
+                    {{"name": "shoot", "from": 1, "to": 3, "direction": "reverse"}},
+                    {{"name": "bob", "from": 0, "to": 3, "direction": "pingpong"}},
+                    {{"name": "blink", "from": 3, "to": 3}},
+                    {{"name": "missing", "from": 5, "to": 6}},
+                    {{"name": "backwards", "from": 2, "to": 1}}
+                ]
+            }}
+        }}"#, frame("a.png", 100), frame("b.png", 50), frame("c.png", 100), frame("d.png", 200))).unwrap();
+
+        let animations = spritesheet.get_tagged_animations();
+        assert_eq!(animations[0], TaggedAnimation{
+            name: "walk".to_string(),
+            frames: vec!["a.png".to_string(), "b.png".to_string(), "c.png".to_string()],
+            frame_times: vec![0.1, 0.05, 0.1],
+        });
+        assert_eq!(animations[1].frames, vec!["d.png", "c.png", "b.png"]);
+        assert_eq!(animations[1].frame_times, vec![0.2, 0.1, 0.05]);
+        // the ends aren't repeated when it turns around
+        assert_eq!(animations[2].frames, vec!["a.png", "b.png", "c.png", "d.png", "c.png", "b.png"]);
+        assert_eq!(animations[3].frames, vec!["d.png"]);
+        // tags without any frames are left out
+        assert_eq!(animations.len(), 4);
+    }
+
+    #[test]
+    fn test_from_json_hash() {
+        let frame = |duration: u32| format!(r#"{{
+            "frame": {{"x":0,"y":0,"w":16,"h":16}},
+            "rotated": false,
+            "trimmed": false,
+            "spriteSourceSize": {{"x":0,"y":0,"w":16,"h":16}},
+            "sourceSize": {{"w":16,"h":16}},
+            "duration": {}
+        }}"#, duration);
+        // keys out of alphabetical order, as the tags go by the order in the file
+        let spritesheet = Spritesheet::from_json(&format!(r#"{{
+            "frames": {{ "walk 1.png": {}, "walk 0.png": {}, "idle.png": {} }},
+            "meta": {{
+                "size": {{"w":48,"h":16}},
+                "frameTags": [{{"name": "walk", "from": 0, "to": 1}}]
+            }}
+        }}"#, frame(100), frame(150), frame(200))).unwrap();
+
+        assert_eq!(spritesheet.get_frame("idle.png").unwrap().duration, Some(200));
+        assert_eq!(spritesheet.get_tagged_animations(), vec![TaggedAnimation{
+            name: "walk".to_string(),
+            frames: vec!["walk 1.png".to_string(), "walk 0.png".to_string()],
+            frame_times: vec![0.1, 0.15],
+        }]);
+    }
+}
@@ -1,36 +1,81 @@
-use std::time::Instant;
-use specs::{WriteStorage, Join, System};
-use components::{AnimationSheet};
-use utils::math;
+use std::ops::DerefMut;
+use specs::{Entities, Entity, Fetch, FetchMut, ReadStorage, WriteStorage, Join, System};
+use components::{AnimationEvent, AnimationEvents, AnimationSheet, AnimationStateMachine, Player, Time};
 
-pub struct AnimationSystem {
-    last_time: Instant,
-}
+pub struct AnimationSystem;
 
-impl AnimationSystem {
-    pub fn new() -> AnimationSystem {
-        AnimationSystem {
-            last_time: Instant::now(),
+// sends the event of the frame the sheet just reached, if it has one
+fn send_frame_event(entity: Entity, animation_sheet: &mut AnimationSheet, animation_events: &mut AnimationEvents) {
+    if animation_sheet.entered_frame {
+        animation_sheet.entered_frame = false;
+        if let Some(name) = animation_sheet.get_frame_event() {
+            animation_events.0.push(AnimationEvent{ entity, name: name.clone() });
         }
     }
 }
 
 impl<'a> System<'a> for AnimationSystem {
     type SystemData = (
-        WriteStorage<'a, AnimationSheet>
+        Entities<'a>,
+        FetchMut<'a, AnimationEvents>,
+        WriteStorage<'a, AnimationSheet>,
+        WriteStorage<'a, AnimationStateMachine>,
+        ReadStorage<'a, Player>,
+        Fetch<'a, Time>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let mut animation_sheet_storage = data;
-
-        for animation_sheet in (&mut animation_sheet_storage).join() {
-            if math::get_mills(&animation_sheet.time_passed.elapsed()) >= animation_sheet.frame_time {
-                animation_sheet.current_index += 1;
-                animation_sheet.time_passed = Instant::now();
-                if animation_sheet.current_index >= animation_sheet.get_current_animation().len() {
-                    animation_sheet.current_index = 0;
-                }
+        let (entities, mut animation_events_storage, mut animation_sheet_storage, mut state_machine_storage, player_storage, time) = data;
+        let animation_events: &mut AnimationEvents = animation_events_storage.deref_mut();
+        animation_events.0.clear();
+
+        for (entity, animation_sheet) in (&*entities, &mut animation_sheet_storage).join() {
+            // set up before the first step, like the starting state
+            send_frame_event(entity, animation_sheet, animation_events);
+            animation_sheet.time_passed += time.delta;
+            // a long frame can pass more than one frame of the animation, each passed frame sends its event
+            while animation_sheet.get_frame_time() > 0.0 && animation_sheet.time_passed >= animation_sheet.get_frame_time() && !animation_sheet.finished {
+                animation_sheet.time_passed -= animation_sheet.get_frame_time();
+                animation_sheet.next_frame();
+                send_frame_event(entity, animation_sheet, animation_events);
+            }
+
+            if let Some(state_machine) = state_machine_storage.get_mut(entity) {
+                let action_state = player_storage.get(entity).map(|player| player.action_state);
+                state_machine.update(action_state, animation_sheet);
             }
+            send_frame_event(entity, animation_sheet, animation_events);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{RunNow, World};
+
+    #[test]
+    fn test_skipped_frame_sends_event() {
+        let mut world = World::new();
+        world.register::<AnimationSheet>();
+        world.register::<AnimationStateMachine>();
+        world.register::<Player>();
+        world.add_resource::<AnimationEvents>(AnimationEvents(Vec::new()));
+        world.add_resource::<Time>(Time::new());
+
+        let mut animation_sheet = AnimationSheet::new(0.1);
+        animation_sheet.add_animation("shoot".to_string(), vec!["shoot_1.png".to_string(), "shoot_2.png".to_string(), "shoot_3.png".to_string()]);
+        animation_sheet.add_frame_event("shoot", 1, "release_arrow");
+        animation_sheet.set_current_animation("shoot".to_string());
+        let entity = world.create_entity().with(animation_sheet).build();
+
+        // a long step passes the second frame on the way to the third
+        world.write_resource::<Time>().update(0.25);
+        AnimationSystem{}.run_now(&world.res);
+
+        assert_eq!(world.read::<AnimationSheet>().get(entity).unwrap().current_index, 2);
+        let animation_events = world.read_resource::<AnimationEvents>();
+        let names: Vec<&str> = animation_events.0.iter().map(|event| event.name.as_ref()).collect();
+        assert_eq!(names, vec!["release_arrow"]);
+    }
+}
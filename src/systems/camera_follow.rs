@@ -0,0 +1,154 @@
+use std::ops::DerefMut;
+use cgmath::Vector2;
+use specs::{Fetch, FetchMut, ReadStorage, System};
+use components::{Action, Camera, Input, TileData, Time, Transform};
+
+// world units a second the view pans at a zoom of 1
+const PAN_SPEED: f32 = 480.0;
+
+/**
+ * Keeps the camera on its target, and pans it with the pan actions or by dragging with the pan drag
+ * action. After panning, the camera stays put until the target moves or the recentre action is pressed.
+ */
+pub struct CameraFollow {
+    // where the target was when the camera was last panned away from it
+    panned_from: Option<Vector2<f32>>,
+}
+
+impl CameraFollow {
+    pub fn new() -> CameraFollow {
+        CameraFollow{
+            panned_from: None,
+        }
+    }
+}
+
+/**
+ * Returns where the bottom left of the view should be so that the target stays within the dead zone around its centre.
+ */
+pub fn get_follow_goal(camera_pos: Vector2<f32>, view_size: Vector2<f32>, dead_zone: Vector2<f32>, target: Vector2<f32>) -> Vector2<f32> {
+    let centre = camera_pos + view_size / 2.0;
+    let mut goal = camera_pos;
+    if target.x > centre.x + dead_zone.x {
+        goal.x += target.x - (centre.x + dead_zone.x);
+    } else if target.x < centre.x - dead_zone.x {
+        goal.x += target.x - (centre.x - dead_zone.x);
+    }
+    if target.y > centre.y + dead_zone.y {
+        goal.y += target.y - (centre.y + dead_zone.y);
+    } else if target.y < centre.y - dead_zone.y {
+        goal.y += target.y - (centre.y - dead_zone.y);
+    }
+    goal
+}
+
+/**
+ * Keeps the view inside the map. When the map is smaller than the view on an axis, the map is centred instead.
+ */
+pub fn clamp_to_map(camera_pos: Vector2<f32>, view_size: Vector2<f32>, map_size: Vector2<f32>) -> Vector2<f32> {
+    let clamp_axis = |pos: f32, view: f32, map: f32| {
+        if map <= view {
+            (map - view) / 2.0
+        } else {
+            pos.clamp(0.0, map - view)
+        }
+    };
+    Vector2::new(
+        clamp_axis(camera_pos.x, view_size.x, map_size.x),
+        clamp_axis(camera_pos.y, view_size.y, map_size.y),
+    )
+}
+
+impl<'a> System<'a> for CameraFollow {
+    type SystemData = (
+        FetchMut<'a, Camera>,
+        FetchMut<'a, Input>,
+        Fetch<'a, TileData>,
+        Fetch<'a, Time>,
+        ReadStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut camera_storage, mut input, tile_data, time, transforms) = data;
+        let camera: &mut Camera = camera_storage.deref_mut();
+        let map_size = Vector2::new(tile_data.map_dimensions[0] as f32, tile_data.map_dimensions[1] as f32);
+        let view_size = camera.get_view_size();
+
+        let mut pan = Vector2::new(0.0, 0.0);
+        if input.is_action_pressed(Action::PanUp) {
+            pan.y += 1.0;
+        }
+        if input.is_action_pressed(Action::PanDown) {
+            pan.y -= 1.0;
+        }
+        if input.is_action_pressed(Action::PanLeft) {
+            pan.x -= 1.0;
+        }
+        if input.is_action_pressed(Action::PanRight) {
+            pan.x += 1.0;
+        }
+
+        let target = camera.target.and_then(|target| transforms.get(target)).map(|transform| Vector2::new(
+            transform.pos.x as f32 + transform.size.x as f32 / 2.0,
+            transform.pos.y as f32 + transform.size.y as f32 / 2.0,
+        ));
+
+        // real time, so the view still pans at full speed while paused or slowed
+        let mut offset = pan * PAN_SPEED / camera.zoom * time.real_delta;
+        for (from, to) in input.take_drags(Action::PanDrag) {
+            // the world under the mouse stays under it
+            offset += camera.screen_to_world(&from) - camera.screen_to_world(&to);
+        }
+        if input.take_action(Action::Recentre) {
+            self.panned_from = None;
+        }
+
+        if offset != Vector2::new(0.0, 0.0) {
+            self.panned_from = target;
+            camera.pos = clamp_to_map(camera.pos + offset, view_size, map_size);
+            return
+        }
+
+        let target = match target {
+            Some(target) => target,
+            None => return,
+        };
+        if self.panned_from == Some(target) {
+            return
+        }
+        self.panned_from = None;
+
+        let goal = get_follow_goal(camera.pos, view_size, camera.dead_zone, target);
+        let goal = clamp_to_map(goal, view_size, map_size);
+        // compounded over the step, so the easing is the same at any --hz
+        let follow = 1.0 - (1.0 - camera.follow_speed).powf(time.delta * 60.0);
+        camera.pos += (goal - camera.pos) * follow;
+        // the clamp is exact, so don't let easing overshoot the map edge
+        camera.pos = clamp_to_map(camera.pos, view_size, map_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_follow_goal() {
+        let view_size = Vector2::new(960.0, 640.0);
+        let dead_zone = Vector2::new(96.0, 64.0);
+        let camera_pos = Vector2::new(0.0, 0.0);
+        // inside the dead zone, the camera stays put
+        assert_eq!(get_follow_goal(camera_pos, view_size, dead_zone, Vector2::new(500.0, 300.0)), camera_pos);
+        assert_eq!(get_follow_goal(camera_pos, view_size, dead_zone, Vector2::new(600.0, 100.0)), Vector2::new(24.0, -156.0));
+    }
+
+    #[test]
+    fn test_clamp_to_map() {
+        let view_size = Vector2::new(960.0, 640.0);
+        let map_size = Vector2::new(2000.0, 1000.0);
+        assert_eq!(clamp_to_map(Vector2::new(-50.0, 500.0), view_size, map_size), Vector2::new(0.0, 360.0));
+        assert_eq!(clamp_to_map(Vector2::new(300.0, 100.0), view_size, map_size), Vector2::new(300.0, 100.0));
+        // a map narrower than the view is centred
+        assert_eq!(clamp_to_map(Vector2::new(300.0, 100.0), view_size, Vector2::new(640.0, 1000.0)), Vector2::new(-160.0, 100.0));
+    }
+}
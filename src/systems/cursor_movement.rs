@@ -0,0 +1,58 @@
+use std::ops::Deref;
+use specs::{Fetch, FetchMut, System};
+use components::{Action, Camera, Input, TileCursor, TileData};
+use utils::movement;
+
+// each cursor action with the direction it moves, y going down
+const CURSOR_ACTIONS: [(Action, (i32, i32)); 4] = [
+    (Action::CursorUp, (0, -1)),
+    (Action::CursorDown, (0, 1)),
+    (Action::CursorLeft, (-1, 0)),
+    (Action::CursorRight, (1, 0)),
+];
+
+/**
+ * Puts the tile cursor under the mouse when it moves or selects, and steps it to the next
+ * tile in a direction when a cursor action is pressed.
+ */
+pub struct CursorMovement {
+    last_mouse_pos: Option<(i32, i32)>,
+}
+
+impl CursorMovement {
+    pub fn new() -> CursorMovement {
+        CursorMovement{
+            last_mouse_pos: None,
+        }
+    }
+}
+
+impl<'a> System<'a> for CursorMovement {
+    type SystemData = (
+        Fetch<'a, Camera>,
+        FetchMut<'a, Input>,
+        FetchMut<'a, TileCursor>,
+        Fetch<'a, TileData>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (camera, mut input, mut tile_cursor, tile_data) = data;
+
+        // the mouse takes over again once it moves, the first frame just records where it is
+        let mouse_moved = self.last_mouse_pos.is_some_and(|pos| pos != input.mouse_pos);
+        self.last_mouse_pos = Some(input.mouse_pos);
+        // the select isn't taken, player movement acts on it
+        if mouse_moved || input.was_action_pressed(Action::Select) {
+            let (x, y) = movement::get_mouse_tile(&input.mouse_pos, camera.deref(), tile_data.deref());
+            if x >= 0 && y >= 0 && x < tile_data.map_size[0] && y < tile_data.map_size[1] {
+                tile_cursor.tile = (x as usize, y as usize);
+            }
+        }
+
+        for &(action, direction) in CURSOR_ACTIONS.iter() {
+            if input.take_action(action) {
+                tile_cursor.move_in_direction(direction, tile_data.deref());
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use specs::{Entity, Fetch, FetchMut, ReadStorage, System};
+use components::{GameState, Player, Time};
+use font::TextAlign;
+use types::Turn;
+use ui::{Ui, UiRect};
+
+const ENERGY_BAR_WIDTH: u16 = 150;
+const ENERGY_COLOR: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+const TEXT_COLOR: [f32; 4] = [1.0; 4];
+
+/**
+ * Panel in the top right with the round, whose turn it is, the player's energy and the end turn button.
+ */
+pub struct Hud {
+    pub player_entity: Entity,
+}
+
+impl<'a> System<'a> for Hud {
+    type SystemData = (
+        FetchMut<'a, GameState>,
+        ReadStorage<'a, Player>,
+        FetchMut<'a, Ui>,
+        Fetch<'a, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut game_state, player_storage, mut ui, time) = data;
+
+        let player = match player_storage.get(self.player_entity) {
+            Some(player) => player,
+            None => return,
+        };
+
+        ui.panel(UiRect::new(560, 560, 396, 76));
+        ui.icon(UiRect::new(568, 570, 28, 56), "player.png");
+
+        ui.label(604, 610, 72, "Energy", TextAlign::Right, TEXT_COLOR);
+        let width = (ENERGY_BAR_WIDTH as f32 * player.get_energy_percentage()) as u16;
+        ui.rect(UiRect::new(684, 606, width, 25), ENERGY_COLOR);
+        ui.label(842, 610, 100, &player.energy.to_string(), TextAlign::Left, TEXT_COLOR);
+
+        ui.label(604, 572, 96, &format!("Round {}", game_state.round), TextAlign::Left, TEXT_COLOR);
+        let turn = match game_state.turn {
+            Turn::Player => "Your turn",
+            Turn::Enemy => "Enemy turn",
+        };
+        ui.label(704, 572, 130, turn, TextAlign::Left, TEXT_COLOR);
+        // the button still takes the click while paused, so it doesn't reach the world
+        if ui.button(UiRect::new(846, 566, 104, 30), "End turn") && !time.paused {
+            game_state.end_turn_requested = true;
+        }
+    }
+}
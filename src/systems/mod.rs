@@ -1,9 +1,21 @@
 mod animation_system;
-mod energy_ui;
+mod camera_follow;
+mod cursor_movement;
+mod hud;
+mod nav_debug;
+mod particle_system;
 mod player_movement;
 mod process_turn;
+mod time_control;
+mod ui_input;
 
 pub use self::animation_system::*;
-pub use self::energy_ui::*;
+pub use self::camera_follow::*;
+pub use self::cursor_movement::*;
+pub use self::hud::*;
+pub use self::nav_debug::*;
+pub use self::particle_system::*;
 pub use self::player_movement::*;
-pub use self::process_turn::*;
\ No newline at end of file
+pub use self::process_turn::*;
+pub use self::time_control::*;
+pub use self::ui_input::*;
\ No newline at end of file
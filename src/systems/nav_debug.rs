@@ -0,0 +1,233 @@
+use std::ops::{Deref, DerefMut};
+use glutin::VirtualKeyCode;
+use specs::{Entities, Entity, Fetch, FetchMut, Join, ReadStorage, WriteStorage, System};
+use cgmath::Vector2;
+use components::{Camera, Color, Input, Player, Rect, ScreenSpace, Text, TileData, Transform};
+use font::TextAlign;
+use math::astar;
+use utils::movement;
+
+pub const NAV_DEBUG_KEY: VirtualKeyCode = VirtualKeyCode::F1;
+
+// each walkable group gets the next colour, wrapping around
+const GROUP_COLORS: [[f32; 4]; 6] = [
+    [0.0, 0.8, 0.2, 0.35],
+    [0.0, 0.5, 1.0, 0.35],
+    [1.0, 0.6, 0.0, 0.35],
+    [0.8, 0.2, 1.0, 0.35],
+    [0.0, 0.9, 0.9, 0.35],
+    [1.0, 0.9, 0.0, 0.35],
+];
+const JUMP_TARGET_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.2];
+const UNPASSABLE_COLOR: [f32; 4] = [0.8, 0.0, 0.0, 0.35];
+const ROUTE_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 0.8];
+// route squares are inset from the tile edges, so the shading under them still shows
+const ROUTE_INSET: i32 = 10;
+
+/**
+ * A shaded tile of the overlay, with rows counted from the top of the map.
+ */
+#[derive(Debug, PartialEq)]
+pub struct OverlayTile {
+    pub tile: (usize, usize),
+    pub color: [f32; 4],
+    pub route: bool,
+}
+
+/**
+ * Tiles of the overlay in drawing order: unpassable tiles, jump targets, walkable groups, then the route.
+ */
+pub fn get_overlay_tiles(tile_data: &TileData, route: &[(usize, usize)]) -> Vec<OverlayTile> {
+    let mut tiles = Vec::new();
+    for (y, row) in tile_data.pathable_grid.iter().enumerate() {
+        for (x, tile_type) in row.iter().enumerate() {
+            if *tile_type == astar::TileType::Unpassable {
+                tiles.push(OverlayTile{ tile: (x, y), color: UNPASSABLE_COLOR, route: false });
+            }
+        }
+    }
+
+    let mut jump_targets: Vec<(usize, usize)> = tile_data.jump_targets.0.iter()
+        .flat_map(|(y, xs)| xs.iter().map(move |x| (*x, *y)))
+        .collect();
+    // the mapping is a hash map, sorted so the overlay doesn't change order between rebuilds
+    jump_targets.sort_by_key(|&(x, y)| (y, x));
+    for tile in jump_targets {
+        tiles.push(OverlayTile{ tile, color: JUMP_TARGET_COLOR, route: false });
+    }
+
+    for (i, group) in tile_data.walkable_groups.iter().enumerate() {
+        let mut group_tiles: Vec<(usize, usize)> = group.0.iter()
+            .flat_map(|(y, xs)| xs.iter().map(move |x| (*x, *y)))
+            .collect();
+        group_tiles.sort_by_key(|&(x, y)| (y, x));
+        for tile in group_tiles {
+            tiles.push(OverlayTile{ tile, color: GROUP_COLORS[i % GROUP_COLORS.len()], route: false });
+        }
+    }
+
+    for tile in route.iter() {
+        tiles.push(OverlayTile{ tile: *tile, color: ROUTE_COLOR, route: true });
+    }
+
+    tiles
+}
+
+/**
+ * Debug overlay of the navigation data, toggled with F1. Shades the tiles the player can walk, jump to and
+ * not pass through, marks the player's last route and shows the tile coordinates under the cursor.
+ */
+pub struct NavDebug {
+    enabled: bool,
+    // the player's movement_route is used up as they move, so the whole route is kept here
+    last_route: Vec<(usize, usize)>,
+    overlay: Vec<Entity>,
+    label: Option<Entity>,
+}
+
+impl NavDebug {
+    pub fn new() -> NavDebug {
+        NavDebug{
+            enabled: false,
+            last_route: Vec::new(),
+            overlay: Vec::new(),
+            label: None,
+        }
+    }
+}
+
+impl<'a> System<'a> for NavDebug {
+    type SystemData = (
+        Entities<'a>,
+        Fetch<'a, Camera>,
+        WriteStorage<'a, Color>,
+        FetchMut<'a, Input>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Rect>,
+        WriteStorage<'a, ScreenSpace>,
+        WriteStorage<'a, Text>,
+        Fetch<'a, TileData>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, camera, mut colors, mut input_storage, players, mut rects, mut screen_spaces, mut texts, tile_data_storage, mut transforms) = data;
+        let input: &mut Input = input_storage.deref_mut();
+        let tile_data: &TileData = tile_data_storage.deref();
+
+        let toggled = input.take_key(NAV_DEBUG_KEY);
+        if toggled {
+            self.enabled = !self.enabled;
+        }
+
+        let mut route_changed = false;
+        for player in players.join() {
+            // a route shrinks from the front as the player moves along it, anything else is a new route
+            if !player.movement_route.is_empty() && !self.last_route.ends_with(&player.movement_route) {
+                self.last_route = player.movement_route.clone();
+                route_changed = true;
+            }
+        }
+
+        if toggled || (self.enabled && route_changed) {
+            for entity in self.overlay.drain(..) {
+                entities.delete(entity);
+            }
+        }
+        if toggled {
+            if let Some(label) = self.label.take() {
+                entities.delete(label);
+            }
+        }
+        if !self.enabled {
+            return
+        }
+
+        if self.overlay.is_empty() {
+            let tile_size = Vector2::new(tile_data.tile_size[0], tile_data.tile_size[1]);
+            for overlay_tile in get_overlay_tiles(tile_data, &self.last_route) {
+                let (x, y) = overlay_tile.tile;
+                let mut transform = Transform::new(
+                    x as i32 * tile_size.x,
+                    tile_data.map_dimensions[1] - (y as i32 + 1) * tile_size.y,
+                    tile_size.x as u16,
+                    tile_size.y as u16,
+                    0.0, 1.0, 1.0
+                );
+                if overlay_tile.route {
+                    transform.pos += Vector2::new(ROUTE_INSET, ROUTE_INSET);
+                    transform.size -= Vector2::new(ROUTE_INSET as u16 * 2, ROUTE_INSET as u16 * 2);
+                    transform.z_order = 1;
+                }
+
+                let entity = entities.create();
+                transforms.insert(entity, transform);
+                rects.insert(entity, Rect{});
+                colors.insert(entity, Color(overlay_tile.color));
+                self.overlay.push(entity);
+            }
+        }
+
+        let label = match self.label {
+            Some(label) => label,
+            None => {
+                let label = entities.create();
+                let mut transform = Transform::new(8, 612, 120, 9, 0.0, 2.0, 2.0);
+                transform.pivot = Vector2::new(0.0, 0.0);
+                transforms.insert(label, transform);
+                texts.insert(label, Text::new("", TextAlign::Left));
+                colors.insert(label, Color([1.0, 1.0, 0.0, 1.0]));
+                screen_spaces.insert(label, ScreenSpace{});
+                self.label = Some(label);
+                label
+            },
+        };
+        if let Some(text) = texts.get_mut(label) {
+            let (x, y) = movement::get_mouse_tile(&input.mouse_pos, camera.deref(), tile_data);
+            text.text = format!("{}, {}", x, y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use types::TileMapping;
+
+    #[test]
+    fn test_get_overlay_tiles() {
+        let mut group = TileMapping(HashMap::new());
+        group.insert(1, vec![0, 1]);
+        let mut other_group = TileMapping(HashMap::new());
+        other_group.insert(0, vec![3]);
+        let mut jump_targets = TileMapping(HashMap::new());
+        jump_targets.insert(0, vec![2]);
+        let tile_data = TileData{
+            walkable_groups: vec![group, other_group],
+            map_size: [4, 3],
+            map_dimensions: [128, 96],
+            tile_size: [32, 32],
+            player_group_index: 0,
+            jump_targets,
+            pathable_grid: vec![
+                vec![astar::TileType::Open; 4],
+                vec![astar::TileType::Open; 4],
+                vec![astar::TileType::Unpassable, astar::TileType::Unpassable, astar::TileType::Open, astar::TileType::Open],
+            ],
+        };
+
+        let tiles = get_overlay_tiles(&tile_data, &[(1, 1), (2, 0)]);
+        let get_tiles = |color: [f32; 4]| -> Vec<(usize, usize)> {
+            tiles.iter().filter(|overlay_tile| overlay_tile.color == color).map(|overlay_tile| overlay_tile.tile).collect()
+        };
+        assert_eq!(get_tiles(UNPASSABLE_COLOR), vec![(0, 2), (1, 2)]);
+        assert_eq!(get_tiles(JUMP_TARGET_COLOR), vec![(2, 0)]);
+        assert_eq!(get_tiles(GROUP_COLORS[0]), vec![(0, 1), (1, 1)]);
+        assert_eq!(get_tiles(GROUP_COLORS[1]), vec![(3, 0)]);
+
+        // the route comes last so it draws over the rest
+        assert_eq!(tiles[tiles.len() - 2], OverlayTile{ tile: (1, 1), color: ROUTE_COLOR, route: true });
+        assert_eq!(tiles[tiles.len() - 1].tile, (2, 0));
+    }
+}
@@ -0,0 +1,156 @@
+extern crate rand;
+
+use std::ops::DerefMut;
+use cgmath::Vector2;
+use self::rand::Rng;
+use specs::{Entities, Fetch, FetchMut, Join, WriteStorage, System};
+use components::{AnimationEvents, BurstKind, ParticleBursts, ParticleEmitter, Particle, Time, Transform};
+
+// frame the bursts' particles use
+pub const BURST_FRAME: &str = "particle.png";
+// animation frame event that kicks up dust at the entity's feet
+pub const STEP_EVENT: &str = "step";
+
+/**
+ * Spawns the bursts asked for this step, and a step burst for each step frame event, then updates
+ * every emitter. Reads frame events, so it must run after the animation system.
+ */
+pub struct ParticleSystem;
+
+fn get_in_range<R: Rng>(rng: &mut R, min: f32, max: f32) -> f32 {
+    if max > min {
+        rng.gen_range(min, max)
+    } else {
+        min
+    }
+}
+
+/**
+ * Ages and moves the emitter's particles, removing those past their lifetime, then spawns new ones at the origin.
+ */
+pub fn update_emitter<R: Rng>(emitter: &mut ParticleEmitter, origin: Vector2<f32>, dt: f32, rng: &mut R) {
+    let gravity = emitter.gravity;
+    for particle in emitter.particles.iter_mut() {
+        particle.age += dt;
+        particle.velocity.y -= gravity * dt;
+        particle.pos += particle.velocity * dt;
+    }
+    emitter.particles.retain(|particle| particle.age < particle.lifetime);
+
+    emitter.spawn_remainder += emitter.spawn_rate * dt;
+    let count = emitter.burst + emitter.spawn_remainder.floor() as usize;
+    emitter.spawn_remainder = emitter.spawn_remainder.fract();
+    emitter.burst = 0;
+
+    let (min_velocity, max_velocity) = emitter.velocity;
+    for _ in 0..count {
+        let particle = Particle{
+            pos: origin,
+            velocity: Vector2::new(
+                get_in_range(rng, min_velocity.x, max_velocity.x),
+                get_in_range(rng, min_velocity.y, max_velocity.y),
+            ),
+            age: 0.0,
+            lifetime: get_in_range(rng, emitter.lifetime.0, emitter.lifetime.1),
+        };
+        emitter.particles.push(particle);
+    }
+}
+
+impl<'a> System<'a> for ParticleSystem {
+    type SystemData = (
+        Entities<'a>,
+        Fetch<'a, AnimationEvents>,
+        FetchMut<'a, ParticleBursts>,
+        WriteStorage<'a, ParticleEmitter>,
+        Fetch<'a, Time>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, animation_events, mut particle_bursts_storage, mut emitters, time, mut transforms) = data;
+        let dt = time.delta;
+
+        let particle_bursts: &mut ParticleBursts = particle_bursts_storage.deref_mut();
+        for event in animation_events.0.iter().filter(|event| event.name == STEP_EVENT) {
+            if let Some(transform) = transforms.get(event.entity) {
+                let feet = Vector2::new(transform.pos.x as f32 + transform.size.x as f32 / 2.0, transform.pos.y as f32);
+                particle_bursts.0.push((BurstKind::Step, feet));
+            }
+        }
+        for (kind, pos) in particle_bursts.0.drain(..) {
+            let entity = entities.create();
+            emitters.insert(entity, ParticleEmitter::new_burst(kind, BURST_FRAME));
+            transforms.insert(entity, Transform::new(pos.x as i32, pos.y as i32, 0, 0, 0.0, 1.0, 1.0));
+        }
+
+        let mut rng = rand::thread_rng();
+        for (entity, emitter, transform) in (&*entities, &mut emitters, &transforms).join() {
+            let origin = Vector2::new(
+                transform.pos.x as f32 + transform.size.x as f32 / 2.0,
+                transform.pos.y as f32 + transform.size.y as f32 / 2.0,
+            );
+            update_emitter(emitter, origin, dt, &mut rng);
+            if emitter.is_finished() {
+                entities.delete(entity);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_emitter() {
+        let mut rng = rand::thread_rng();
+        let mut emitter = ParticleEmitter::new("particle.png");
+        emitter.spawn_rate = 10.0;
+        emitter.lifetime = (1.0, 1.0);
+        emitter.velocity = (Vector2::new(10.0, 20.0), Vector2::new(10.0, 20.0));
+        emitter.gravity = 10.0;
+
+        // a quarter of a second owes two and a half particles
+        update_emitter(&mut emitter, Vector2::new(0.0, 0.0), 0.25, &mut rng);
+        assert_eq!(emitter.particles.len(), 2);
+        assert_eq!(emitter.spawn_remainder, 0.5);
+
+        emitter.spawn_rate = 0.0;
+        update_emitter(&mut emitter, Vector2::new(0.0, 0.0), 0.5, &mut rng);
+        let particle = &emitter.particles[0];
+        assert_eq!(particle.velocity, Vector2::new(10.0, 15.0));
+        assert_eq!(particle.pos, Vector2::new(5.0, 7.5));
+        assert_eq!(emitter.get_color(particle), [1.0, 1.0, 1.0, 0.5]);
+
+        update_emitter(&mut emitter, Vector2::new(0.0, 0.0), 0.5, &mut rng);
+        assert!(emitter.particles.is_empty());
+    }
+
+    #[test]
+    fn test_burst_finishes() {
+        let mut rng = rand::thread_rng();
+        let mut emitter = ParticleEmitter::new_burst(BurstKind::Land, "particle.png");
+        assert!(!emitter.is_finished());
+        update_emitter(&mut emitter, Vector2::new(0.0, 0.0), 0.0, &mut rng);
+        assert_eq!(emitter.particles.len(), 16);
+        for particle in emitter.particles.iter() {
+            assert!(particle.lifetime >= 0.3 && particle.lifetime <= 0.5);
+        }
+        update_emitter(&mut emitter, Vector2::new(0.0, 0.0), 1.0, &mut rng);
+        assert!(emitter.is_finished());
+    }
+
+    #[test]
+    fn test_hit_burst() {
+        let mut rng = rand::thread_rng();
+        let mut emitter = ParticleEmitter::new_burst(BurstKind::Hit, "particle.png");
+        update_emitter(&mut emitter, Vector2::new(0.0, 0.0), 0.0, &mut rng);
+        assert_eq!(emitter.particles.len(), 10);
+        // sparks aren't pulled down
+        update_emitter(&mut emitter, Vector2::new(0.0, 0.0), 0.1, &mut rng);
+        for particle in emitter.particles.iter() {
+            assert_eq!(particle.pos, particle.velocity * 0.1);
+        }
+    }
+}
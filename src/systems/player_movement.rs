@@ -1,20 +1,29 @@
 use std::ops::{Deref, DerefMut};
+use cgmath::Vector2;
 use specs::{Fetch, FetchMut, Join, ReadStorage, WriteStorage, System};
-use components::{Input, GameState, HighlightTile, Player, PlayerActionState, Sprite, TileData, Transform};
+use components::{Action, BurstKind, Input, GameState, HighlightTile, ParticleBursts, Player, PlayerActionState, Sprite, TileCursor, TileData, Time, Transform};
 use math::astar;
 use types::{TileMapping, Turn};
 use utils::movement;
 
-pub struct PlayerMovement{
-    pub pathable_grid: Vec<Vec<astar::TileType>>,
+// seconds between steps along the route
+const STEP_TIME: f32 = 1.0 / 60.0;
+
+/**
+ * Moves the player along a route to the tile cursor when selected, and highlights the tile if it
+ * can be reached. Cancel stops a walk at the tile it has reached and hides the highlight until the
+ * cursor moves. Jumps and landings carry on, as they can't stop in the air.
+ */
+pub struct PlayerMovement {
+    // the cursor tile the highlight was cancelled on
+    cancelled_tile: Option<(usize, usize)>,
 }
 
 impl PlayerMovement {
-    fn get_mouse_tile(&self, mouse_pos: &(i32, i32), tile_size: &[i32; 2]) -> (i32, i32) {
-        (
-            mouse_pos.0 / tile_size[0],
-            mouse_pos.1 / tile_size[1],
-        )
+    pub fn new() -> PlayerMovement {
+        PlayerMovement{
+            cancelled_tile: None,
+        }
     }
 
     fn get_cost_for_move(&self, distance: usize, action: &PlayerActionState) -> usize {
@@ -35,18 +44,23 @@ impl PlayerMovement {
         cost
     }
 
-    fn move_highlight_to_mouse(&self, mouse_tile: &(usize, usize), transform: &mut Transform, tile_data: &TileData, sprite: &mut Sprite) {
+    fn move_highlight_to_cursor(&self, cursor_tile: &(usize, usize), transform: &mut Transform, tile_data: &TileData, sprite: &mut Sprite) {
         sprite.visible = true;
-        transform.pos.x = mouse_tile.0 as i32 * tile_data.tile_size[1];
-        transform.pos.y = tile_data.map_dimensions[1] - (mouse_tile.1 as i32 * tile_data.tile_size[1]) - tile_data.tile_size[1];
+        transform.pos.x = cursor_tile.0 as i32 * tile_data.tile_size[1];
+        transform.pos.y = tile_data.map_dimensions[1] - (cursor_tile.1 as i32 * tile_data.tile_size[1]) - tile_data.tile_size[1];
     }
 
-    fn astar_path_to_mouse(&self, player_transform: &Transform, tile_data: &TileData, mouse_tile: (usize, usize)) -> Vec<(usize, usize)> {
+    // world position of the bottom centre of the transform
+    fn get_feet(&self, transform: &Transform) -> Vector2<f32> {
+        Vector2::new(transform.pos.x as f32 + transform.size.x as f32 / 2.0, transform.pos.y as f32)
+    }
+
+    fn astar_path_to_cursor(&self, player_transform: &Transform, tile_data: &TileData, cursor_tile: (usize, usize)) -> Vec<(usize, usize)> {
         let player_tile = tile_data.get_tile_for_world_position(&player_transform.pos);
         astar::find_path(
-            &self.pathable_grid,
+            &tile_data.pathable_grid,
             player_tile,
-            mouse_tile
+            cursor_tile
         )
     }
 }
@@ -55,25 +69,33 @@ impl<'a> System<'a> for PlayerMovement {
     type SystemData = (
         Fetch<'a, GameState>,
         ReadStorage<'a, HighlightTile>,
-        Fetch<'a, Input>,
+        FetchMut<'a, Input>,
+        FetchMut<'a, ParticleBursts>,
         WriteStorage<'a, Player>,
         WriteStorage<'a, Sprite>,
+        Fetch<'a, TileCursor>,
         FetchMut<'a, TileData>,
+        Fetch<'a, Time>,
         WriteStorage<'a, Transform>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (game_state_storage, highlight_tile_storage, input_storage, mut players, mut sprites, mut tile_data_storage, mut transforms) = data;
+        let (game_state_storage, highlight_tile_storage, mut input_storage, mut particle_bursts, mut players, mut sprites, tile_cursor, mut tile_data_storage, time, mut transforms) = data;
 
         let game_state: &GameState = game_state_storage.deref();
         if game_state.turn == Turn::Enemy {
             return
         }
-        let input: &Input = input_storage.deref();
+        let input: &mut Input = input_storage.deref_mut();
         let tile_data: &mut TileData = tile_data_storage.deref_mut();
 
-        let mouse_tile = self.get_mouse_tile(&input.mouse_pos, &tile_data.tile_size);
-        let mouse_tile = (mouse_tile.0 as usize, mouse_tile.1 as usize);
+        let cursor_tile = tile_cursor.tile;
+        let cancelled = !time.paused && input.take_action(Action::Cancel);
+        if cancelled {
+            self.cancelled_tile = Some(cursor_tile);
+        } else if self.cancelled_tile != Some(cursor_tile) {
+            self.cancelled_tile = None;
+        }
 
         let mut player_in_air = false;
         let mut player_distance = 0;
@@ -83,72 +105,183 @@ impl<'a> System<'a> for PlayerMovement {
             player_in_air = player.in_air();
             player_jump_distance = player.jump_distance;
             let player_tile = tile_data.get_tile_for_world_position(&transform.pos);
-            player_distance = movement::distance_to_tile(&player_tile, &mouse_tile, false);
-            if input.mouse_pressed && !player.moving() && !player.jumping() {
+            player_distance = movement::distance_to_tile(&player_tile, &cursor_tile, false);
+            // a press while moving is dropped rather than starting another move when this one ends,
+            // and presses while paused are left alone
+            if !time.paused && !player.moving() && !player.jumping() && (input.take_action(Action::Select) || input.take_action(Action::Confirm)) {
+                let mut found = false;
                 if player.in_air() && player_distance <= player.jump_distance {
                     for (i, group) in tile_data.walkable_groups.iter().enumerate() {
-                        if group.contains(&mouse_tile.1, &mouse_tile.0) {
+                        if group.contains(&cursor_tile.1, &cursor_tile.0) {
                             player.action_state = PlayerActionState::Moving;
-                            player.movement_route = self.astar_path_to_mouse(&transform, &tile_data, mouse_tile);
+                            player.movement_route = self.astar_path_to_cursor(&transform, &tile_data, cursor_tile);
                             tile_data.player_group_index = i;
+                            player.landing = true;
+                            found = true;
                             break
                         }
                     }
                 } else {
                     let group = &tile_data.walkable_groups[tile_data.player_group_index];
-                    let mut found = false;
-                    if group.contains(&mouse_tile.1, &mouse_tile.0) {
+                    if group.contains(&cursor_tile.1, &cursor_tile.0) {
                         player.action_state = PlayerActionState::Moving;
                         found = true;
-                    } else if tile_data.jump_targets.contains(&mouse_tile.1, &mouse_tile.0) {
+                    } else if tile_data.jump_targets.contains(&cursor_tile.1, &cursor_tile.0) {
                         player.action_state = PlayerActionState::Jumping;
+                        particle_bursts.0.push((BurstKind::Jump, self.get_feet(&transform)));
                         found = true;
                     }
 
                     if found {
-                        player.movement_route = self.astar_path_to_mouse(&transform, &tile_data, mouse_tile);
+                        player.movement_route = self.astar_path_to_cursor(&transform, &tile_data, cursor_tile);
                     }
                 }
-                let cost = self.get_cost_for_move(movement::distance_to_tile(&player_tile, &mouse_tile, true), &player.action_state);
-                player.take_energy(cost);
+                // a press that doesn't start a move costs nothing
+                if found {
+                    let cost = self.get_cost_for_move(movement::distance_to_tile(&player_tile, &cursor_tile, true), &player.action_state);
+                    player.take_energy(cost);
+                }
             } else if player.moving() || player.jumping() {
-                // will need to track this differently to lerp at somepoint
-                let mut done = false;
-                if let Some(next_pos) = player.movement_route.iter().next() {
-                    transform.pos.x = next_pos.0 as i32 * tile_data.tile_size[0];
-                    transform.pos.y = tile_data.map_dimensions[1] - (next_pos.1 as i32 * tile_data.tile_size[1]) - tile_data.tile_size[1];
-                } else {
-                    if player.moving() {
-                        player.action_state = PlayerActionState::OnGround;
-                    } else if player.jumping() {
-                        player.action_state = PlayerActionState::InAir;
-                    }
-                    done = true;
+                if cancelled && player.moving() && !player.landing {
+                    player.movement_route.clear();
                 }
+                player.step_timer += time.delta;
+                while player.step_timer >= STEP_TIME && (player.moving() || player.jumping()) {
+                    player.step_timer -= STEP_TIME;
+                    // will need to track this differently to lerp at somepoint
+                    let mut done = false;
+                    if let Some(next_pos) = player.movement_route.iter().next() {
+                        // face the direction of travel, keeping the last facing when moving straight up or down
+                        let next_x = next_pos.0 as i32 * tile_data.tile_size[0];
+                        if next_x != transform.pos.x {
+                            transform.flip_x = next_x < transform.pos.x;
+                        }
+                        transform.pos.x = next_x;
+                        transform.pos.y = tile_data.map_dimensions[1] - (next_pos.1 as i32 * tile_data.tile_size[1]) - tile_data.tile_size[1];
+                    } else {
+                        if player.moving() {
+                            player.action_state = PlayerActionState::OnGround;
+                            if player.landing {
+                                player.landing = false;
+                                particle_bursts.0.push((BurstKind::Land, self.get_feet(&transform)));
+                            }
+                        } else if player.jumping() {
+                            player.action_state = PlayerActionState::InAir;
+                        }
+                        player.step_timer = 0.0;
+                        done = true;
+                    }
 
-                // passed through a position
-                if !done {
-                    player.movement_route.remove(0);
+                    // passed through a position
+                    if !done {
+                        player.movement_route.remove(0);
+                    }
                 }
             }
         }
 
         for (_, mut sprite, mut transform) in (&highlight_tile_storage, &mut sprites, &mut transforms).join() {
             sprite.visible = false;
+            if self.cancelled_tile.is_some() {
+                continue
+            }
             if player_in_air && player_distance <= player_jump_distance {
                 for group in &tile_data.walkable_groups {
-                    if group.contains(&mouse_tile.1, &mouse_tile.0) {
-                        self.move_highlight_to_mouse(&mouse_tile, &mut transform, &tile_data, &mut sprite);
+                    if group.contains(&cursor_tile.1, &cursor_tile.0) {
+                        self.move_highlight_to_cursor(&cursor_tile, &mut transform, &tile_data, &mut sprite);
                     }
                 }
             } else if !player_in_air {
                 let group = &tile_data.walkable_groups[tile_data.player_group_index];
-                if group.contains(&mouse_tile.1, &mouse_tile.0) {
-                    self.move_highlight_to_mouse(&mouse_tile, &mut transform, &tile_data, &mut sprite);
-                } else if player_distance <= player_jump_distance && tile_data.jump_targets.contains(&mouse_tile.1, &mouse_tile.0) {
-                    self.move_highlight_to_mouse(&mouse_tile, &mut transform, &tile_data, &mut sprite);
+                if group.contains(&cursor_tile.1, &cursor_tile.0) {
+                    self.move_highlight_to_cursor(&cursor_tile, &mut transform, &tile_data, &mut sprite);
+                } else if player_distance <= player_jump_distance && tile_data.jump_targets.contains(&cursor_tile.1, &cursor_tile.0) {
+                    self.move_highlight_to_cursor(&cursor_tile, &mut transform, &tile_data, &mut sprite);
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use specs::{RunNow, World};
+    use components::{ActionMap, InputEvent};
+    use math::astar::TileType;
+
+    // the player standing on the left of a row of four walkable tiles
+    fn build_world() -> World {
+        let mut world = World::new();
+        world.register::<HighlightTile>();
+        world.register::<Player>();
+        world.register::<Sprite>();
+        world.register::<Transform>();
+        world.add_resource::<GameState>(GameState::new());
+        world.add_resource::<Input>(Input::new(1.0, ActionMap::new()));
+        world.add_resource::<ParticleBursts>(ParticleBursts(Vec::new()));
+        world.add_resource::<TileCursor>(TileCursor::new((3, 2)));
+        world.add_resource::<Time>(Time::new());
+
+        let mut walkable = TileMapping(HashMap::new());
+        walkable.insert(2, vec![0, 1, 2, 3]);
+        world.add_resource::<TileData>(TileData{
+            walkable_groups: vec![walkable],
+            map_size: [4, 3],
+            map_dimensions: [128, 96],
+            tile_size: [32, 32],
+            player_group_index: 0,
+            jump_targets: TileMapping(HashMap::new()),
+            pathable_grid: vec![vec![TileType::Open; 4]; 3],
+        });
+        world.create_entity()
+            .with(Transform::new(0, 32, 32, 64, 0.0, 1.0, 1.0))
+            .with(Player::new())
+            .build();
+        world
+    }
+
+    #[test]
+    fn test_paused_select_does_nothing() {
+        let mut world = build_world();
+        world.write_resource::<Time>().paused = true;
+        world.write_resource::<Time>().update(1.0 / 60.0);
+        world.write_resource::<Input>().events.push(InputEvent::ActionPressed(Action::Select));
+        PlayerMovement::new().run_now(&world.res);
+        {
+            let players = world.read::<Player>();
+            let player = players.join().next().unwrap();
+            assert!(player.movement_route.is_empty());
+            assert!(!player.moving());
+            assert_eq!(player.energy, Player::new().energy);
+        }
+        // not taken, and the same press starts a move once unpaused
+        assert!(world.read_resource::<Input>().was_action_pressed(Action::Select));
+
+        world.write_resource::<Time>().paused = false;
+        PlayerMovement::new().run_now(&world.res);
+        let players = world.read::<Player>();
+        let player = players.join().next().unwrap();
+        assert!(player.moving());
+        assert!(player.energy < Player::new().energy);
+    }
+
+    #[test]
+    fn test_cancel_stops_walk() {
+        let mut world = build_world();
+        let mut player_movement = PlayerMovement::new();
+        world.write_resource::<Time>().update(0.0);
+        world.write_resource::<Input>().events.push(InputEvent::ActionPressed(Action::Select));
+        player_movement.run_now(&world.res);
+        assert!(world.read::<Player>().join().next().unwrap().moving());
+
+        world.write_resource::<Time>().update(0.1);
+        world.write_resource::<Input>().events = vec![InputEvent::ActionPressed(Action::Cancel)];
+        player_movement.run_now(&world.res);
+        let players = world.read::<Player>();
+        let player = players.join().next().unwrap();
+        assert!(player.movement_route.is_empty());
+        assert_eq!(player.action_state, PlayerActionState::OnGround);
+        assert_eq!(player_movement.cancelled_tile, Some((3, 2)));
+    }
+}
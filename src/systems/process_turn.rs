@@ -1,6 +1,6 @@
-use std::ops::{Deref, DerefMut};
+use std::ops::DerefMut;
 use specs::{Fetch, FetchMut, Join, ReadStorage, WriteStorage, System};
-use components::{GameState, HighlightTile, Player, Sprite};
+use components::{Action, GameState, HighlightTile, Input, Player, Sprite, Time};
 use types::Turn;
 
 pub struct ProcessTurn;
@@ -8,24 +8,49 @@ pub struct ProcessTurn;
 impl<'a> System<'a> for ProcessTurn {
     type SystemData = (
         FetchMut<'a, GameState>,
+        FetchMut<'a, Input>,
         WriteStorage<'a, Player>,
         ReadStorage<'a, HighlightTile>,
         WriteStorage<'a, Sprite>,
+        Fetch<'a, Time>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut game_state_storage, mut player_storage, highlight_tile_storage, mut sprite_storage) = data;
+        let (mut game_state_storage, mut input, mut player_storage, highlight_tile_storage, mut sprite_storage, time) = data;
         let game_state: &mut GameState = game_state_storage.deref_mut();
+        // turns wait while paused
+        if time.paused {
+            return
+        }
+
+        // kept until the turn ends, so a request during a move ends the turn once it finishes
+        if input.take_action(Action::EndTurn) && game_state.turn == Turn::Player {
+            game_state.end_turn_requested = true;
+        }
 
         for player in (&mut player_storage).join() {
-            if game_state.turn == Turn::Player && player.energy == 0 {
-                game_state.turn = Turn::Enemy;
-                for (_, sprite) in (&highlight_tile_storage, &mut sprite_storage).join() {
-                    if sprite.visible {
-                        sprite.visible = false;
+            match game_state.turn {
+                Turn::Player => {
+                    // a move in progress finishes first
+                    let can_end = !player.moving() && !player.jumping();
+                    if player.energy == 0 || (game_state.end_turn_requested && can_end) {
+                        player.end_turn(game_state.bank_rate);
+                        game_state.turn = Turn::Enemy;
+                        game_state.end_turn_requested = false;
+                        for (_, sprite) in (&highlight_tile_storage, &mut sprite_storage).join() {
+                            if sprite.visible {
+                                sprite.visible = false;
+                            }
+                        }
                     }
-                }
+                },
+                Turn::Enemy => {
+                    // enemies don't act yet, so their turn passes straight back
+                    game_state.turn = Turn::Player;
+                    game_state.round += 1;
+                    player.start_turn();
+                },
             }
         }
     }
-}
\ No newline at end of file
+}
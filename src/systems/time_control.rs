@@ -0,0 +1,29 @@
+use specs::{FetchMut, System};
+use components::{Action, Input, Time};
+
+// time scale while slow motion is on
+pub const SLOW_MOTION_SCALE: f32 = 0.25;
+
+/**
+ * Toggles pausing and slow motion with their actions. They take effect from the next step, as this
+ * one's delta is already set.
+ */
+pub struct TimeControl;
+
+impl<'a> System<'a> for TimeControl {
+    type SystemData = (
+        FetchMut<'a, Input>,
+        FetchMut<'a, Time>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut input, mut time) = data;
+
+        if input.take_action(Action::Pause) {
+            time.paused = !time.paused;
+        }
+        if input.take_action(Action::SlowMotion) {
+            time.time_scale = if time.time_scale < 1.0 { 1.0 } else { SLOW_MOTION_SCALE };
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use std::ops::DerefMut;
+use specs::{Fetch, FetchMut, System};
+use components::{Action, Camera, Input};
+use ui::Ui;
+
+/**
+ * Starts the ui's step with the mouse, taking selects over the ui before anything in the world sees them.
+ */
+pub struct UiInput;
+
+impl<'a> System<'a> for UiInput {
+    type SystemData = (
+        Fetch<'a, Camera>,
+        FetchMut<'a, Input>,
+        FetchMut<'a, Ui>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (camera, mut input_storage, mut ui_storage) = data;
+        let input: &mut Input = input_storage.deref_mut();
+        let ui: &mut Ui = ui_storage.deref_mut();
+
+        ui.begin(camera.screen_to_ui(&input.mouse_pos), input.is_action_pressed(Action::Select));
+        if ui.is_mouse_over() && input.take_action(Action::Select) {
+            ui.click();
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! Immediate mode ui, rebuilt by systems every step and drawn over everything in design
+//! resolution coordinates, from the bottom left like the world.
+
+extern crate specs;
+
+use cgmath::Vector2;
+use specs::{Component, HashMapStorage};
+use components::{Text, Transform};
+use font::TextAlign;
+
+// the font is drawn at double size
+pub const TEXT_SCALE: f32 = 2.0;
+// the font's line height, before scaling
+const LINE_HEIGHT: u16 = 9;
+const PANEL_COLOR: [f32; 4] = [0.1, 0.09, 0.14, 0.85];
+const BUTTON_COLOR: [f32; 4] = [0.25, 0.22, 0.35, 1.0];
+const BUTTON_HOVER_COLOR: [f32; 4] = [0.35, 0.31, 0.5, 1.0];
+const BUTTON_PRESSED_COLOR: [f32; 4] = [0.18, 0.16, 0.26, 1.0];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiRect {
+    pub x: i32,
+    pub y: i32,
+    pub w: u16,
+    pub h: u16,
+}
+
+impl UiRect {
+    pub fn new(x: i32, y: i32, w: u16, h: u16) -> UiRect {
+        UiRect{ x, y, w, h }
+    }
+
+    pub fn contains(&self, pos: Vector2<f32>) -> bool {
+        pos.x >= self.x as f32 && pos.x < (self.x + self.w as i32) as f32 &&
+            pos.y >= self.y as f32 && pos.y < (self.y + self.h as i32) as f32
+    }
+
+    fn get_transform(&self) -> Transform {
+        Transform::new(self.x, self.y, self.w, self.h, 0.0, 1.0, 1.0)
+    }
+}
+
+pub enum UiElement {
+    Rect(Transform, [f32; 4]),
+    Icon(Transform, String),
+    Label(Transform, Text, [f32; 4]),
+}
+
+/**
+ * Elements to draw this step, in order. Panels and buttons catch clicks, using where they were
+ * drawn the step before, so a click on them doesn't reach the world too.
+ */
+pub struct Ui {
+    pub elements: Vec<UiElement>,
+    mouse_pos: Vector2<f32>,
+    mouse_down: bool,
+    // click caught by the ui this step, until a button takes it
+    click: Option<Vector2<f32>>,
+    hit_areas: Vec<UiRect>,
+    next_hit_areas: Vec<UiRect>,
+}
+
+impl Ui {
+    pub fn new() -> Ui {
+        Ui{
+            elements: Vec::new(),
+            mouse_pos: Vector2::new(-1.0, -1.0),
+            mouse_down: false,
+            click: None,
+            hit_areas: Vec::new(),
+            next_hit_areas: Vec::new(),
+        }
+    }
+
+    /**
+     * Starts a step, clearing the last one's elements. The mouse position is in ui coordinates.
+     */
+    pub fn begin(&mut self, mouse_pos: Vector2<f32>, mouse_down: bool) {
+        self.elements.clear();
+        self.hit_areas = self.next_hit_areas.drain(..).collect();
+        self.mouse_pos = mouse_pos;
+        self.mouse_down = mouse_down;
+        self.click = None;
+    }
+
+    /**
+     * Whether the mouse is over a panel or button drawn last step, so a click there belongs to the ui.
+     */
+    pub fn is_mouse_over(&self) -> bool {
+        self.hit_areas.iter().any(|rect| rect.contains(self.mouse_pos))
+    }
+
+    /**
+     * Gives the ui a click at the mouse position, for a button to take.
+     */
+    pub fn click(&mut self) {
+        self.click = Some(self.mouse_pos);
+    }
+
+    pub fn rect(&mut self, rect: UiRect, color: [f32; 4]) {
+        self.elements.push(UiElement::Rect(rect.get_transform(), color));
+    }
+
+    /**
+     * Background for other elements, catching clicks on it.
+     */
+    pub fn panel(&mut self, rect: UiRect) {
+        self.rect(rect, PANEL_COLOR);
+        self.next_hit_areas.push(rect);
+    }
+
+    /**
+     * A spritesheet frame stretched over the rect.
+     */
+    pub fn icon(&mut self, rect: UiRect, frame_name: &str) {
+        self.elements.push(UiElement::Icon(rect.get_transform(), frame_name.to_string()));
+    }
+
+    /**
+     * A line of text with its bottom left at x and y, aligned within the width.
+     */
+    pub fn label(&mut self, x: i32, y: i32, width: u16, text: &str, align: TextAlign, color: [f32; 4]) {
+        let mut transform = Transform::new(x, y, (width as f32 / TEXT_SCALE) as u16, LINE_HEIGHT, 0.0, TEXT_SCALE, TEXT_SCALE);
+        transform.pivot = Vector2::new(0.0, 0.0);
+        self.elements.push(UiElement::Label(transform, Text::new(text, align), color));
+    }
+
+    /**
+     * A button with a centred label, lit up while hovered and darker while pressed. Returns
+     * whether it was clicked this step.
+     */
+    pub fn button(&mut self, rect: UiRect, text: &str) -> bool {
+        let hovered = rect.contains(self.mouse_pos);
+        let color = if hovered && self.mouse_down {
+            BUTTON_PRESSED_COLOR
+        } else if hovered {
+            BUTTON_HOVER_COLOR
+        } else {
+            BUTTON_COLOR
+        };
+        self.rect(rect, color);
+        let text_height = (LINE_HEIGHT as f32 * TEXT_SCALE) as i32;
+        self.label(rect.x, rect.y + (rect.h as i32 - text_height) / 2, rect.w, text, TextAlign::Center, [1.0; 4]);
+        self.next_hit_areas.push(rect);
+
+        match self.click {
+            Some(pos) if rect.contains(pos) => {
+                self.click = None;
+                true
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Component for Ui {
+    type Storage = HashMapStorage<Ui>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_button_click() {
+        let mut ui = Ui::new();
+        let rect = UiRect::new(10, 10, 100, 30);
+
+        // nothing was drawn last step, so the first click goes to the world
+        ui.begin(Vector2::new(20.0, 20.0), false);
+        assert!(!ui.is_mouse_over());
+        assert!(!ui.button(rect, "End turn"));
+
+        ui.begin(Vector2::new(20.0, 20.0), true);
+        assert!(ui.is_mouse_over());
+        ui.click();
+        assert!(ui.button(rect, "End turn"));
+        // taken by the first button under it
+        assert!(!ui.button(rect, "End turn"));
+        match ui.elements[0] {
+            UiElement::Rect(_, color) => assert_eq!(color, BUTTON_PRESSED_COLOR),
+            _ => panic!("expected the button's rect first"),
+        }
+
+        ui.begin(Vector2::new(200.0, 20.0), false);
+        assert!(!ui.is_mouse_over());
+    }
+}
@@ -0,0 +1,58 @@
+//! Runs the game logic at a fixed rate, independent of how often frames are drawn.
+
+pub const DEFAULT_HZ: f32 = 60.0;
+// most steps run for one frame, past this the game slows down instead of falling further behind
+const MAX_STEPS: u32 = 5;
+
+pub struct FixedStep {
+    // seconds per step
+    pub step: f32,
+    // real time not yet used up by steps
+    accumulator: f32,
+}
+
+impl FixedStep {
+    pub fn new(hz: f32) -> FixedStep {
+        FixedStep{
+            step: 1.0 / hz,
+            accumulator: 0.0,
+        }
+    }
+
+    /**
+     * Adds the frame's time, returning how many steps to run for it. Time beyond MAX_STEPS
+     * is dropped, so a slow frame can't cause more steps that make the next frame slower still.
+     */
+    pub fn advance(&mut self, frame_time: f32) -> u32 {
+        self.accumulator = (self.accumulator + frame_time).min(self.step * MAX_STEPS as f32);
+        let steps = (self.accumulator / self.step).floor() as u32;
+        self.accumulator -= steps as f32 * self.step;
+        steps
+    }
+
+    /**
+     * How far between the last step and the next the current time is, from 0 to 1,
+     * for drawing between the last two steps' positions.
+     */
+    pub fn get_alpha(&self) -> f32 {
+        (self.accumulator / self.step).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance() {
+        let mut fixed_step = FixedStep::new(10.0);
+        assert_eq!(fixed_step.advance(0.05), 0);
+        assert!((fixed_step.get_alpha() - 0.5).abs() < 0.0001);
+        assert_eq!(fixed_step.advance(0.175), 2);
+        assert!((fixed_step.get_alpha() - 0.25).abs() < 0.0001);
+
+        // a long stall only runs the capped number of steps, and drops the rest
+        assert_eq!(fixed_step.advance(3.0), MAX_STEPS);
+        assert!(fixed_step.get_alpha() < 0.0001);
+    }
+}
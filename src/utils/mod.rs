@@ -1,3 +1,5 @@
 pub mod tiled;
 pub mod movement;
-pub mod math;
\ No newline at end of file
+pub mod math;
+pub mod watcher;
+pub mod fixed_step;
\ No newline at end of file
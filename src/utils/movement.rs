@@ -3,6 +3,7 @@
 */
 
 use std::cmp::max;
+use components::{Camera, TileData};
 
 pub fn distance_to_tile(from: &(usize, usize), to: &(usize, usize), total: bool) -> usize {
     let y_diff = (from.1 as i32 - to.1 as i32).abs() as usize;
@@ -11,4 +12,15 @@ pub fn distance_to_tile(from: &(usize, usize), to: &(usize, usize), total: bool)
         return x_diff + y_diff
     }
     max(x_diff as usize, y_diff as usize)
-}
\ No newline at end of file
+}
+
+/**
+ * Tile under the mouse, with rows counted from the top of the map. Can be outside the map.
+ */
+pub fn get_mouse_tile(mouse_pos: &(i32, i32), camera: &Camera, tile_data: &TileData) -> (i32, i32) {
+    let world_pos = camera.screen_to_world(mouse_pos);
+    (
+        (world_pos.x / tile_data.tile_size[0] as f32).floor() as i32,
+        ((tile_data.map_dimensions[1] as f32 - world_pos.y) / tile_data.tile_size[1] as f32).floor() as i32,
+    )
+}
@@ -1,14 +1,101 @@
 extern crate gfx;
 extern crate tiled;
 
+use cgmath::Vector2;
 use linked_hash_map::LinkedHashMap;
+use xml::reader::{EventReader, XmlEvent};
+use xml::attribute::OwnedAttribute;
 
 use std::collections::HashMap;
+use std::io::Read;
+use components::TileData;
+use math::astar;
 use renderer;
-use renderer::{TileMapPlane, PlaneRenderer};
+use renderer::{BackdropRenderer, TileMapRenderer};
 use types::TileMapping;
 
 const COLLISION_LAYERS: [&str; 1] = ["ground"];
+const SPAWN_GROUP: &str = "spawns";
+// marker layer for where entities are drawn, layers above it render in front of them
+pub const ENTITY_LAYER: &str = "entities";
+const FOREGROUND_PROPERTY: &str = "foreground";
+// fraction of the camera's movement a layer scrolls by, 1.0 being the default
+const PARALLAX_PROPERTY: &str = "parallax";
+
+/**
+ * Image layer from the map. The tiled crate skips these, so they are read from the tmx separately.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageLayer {
+    pub name: String,
+    pub source: String,
+    pub width: u32,
+    pub height: u32,
+    pub offset: [f32; 2],
+    pub opacity: f32,
+    pub visible: bool,
+    pub parallax: f32,
+}
+
+fn get_attribute<T: ::std::str::FromStr>(attributes: &[OwnedAttribute], name: &str) -> Option<T> {
+    attributes.iter()
+        .find(|attribute| attribute.name.local_name == name)
+        .and_then(|attribute| attribute.value.parse().ok())
+}
+
+pub fn parse_image_layers<R: Read>(reader: R) -> Vec<ImageLayer> {
+    let mut image_layers: Vec<ImageLayer> = Vec::new();
+    let mut current: Option<ImageLayer> = None;
+
+    for event in EventReader::new(reader) {
+        match event {
+            Ok(XmlEvent::StartElement{ name, attributes, .. }) => {
+                if name.local_name == "imagelayer" {
+                    current = Some(ImageLayer{
+                        name: get_attribute(&attributes, "name").unwrap_or_default(),
+                        source: String::new(),
+                        width: 0,
+                        height: 0,
+                        offset: [get_attribute(&attributes, "offsetx").unwrap_or(0.0), get_attribute(&attributes, "offsety").unwrap_or(0.0)],
+                        opacity: get_attribute(&attributes, "opacity").unwrap_or(1.0),
+                        visible: get_attribute::<u32>(&attributes, "visible").unwrap_or(1) == 1,
+                        parallax: 1.0,
+                    });
+                } else if let Some(ref mut image_layer) = current {
+                    if name.local_name == "image" {
+                        image_layer.source = get_attribute(&attributes, "source").unwrap_or_default();
+                        image_layer.width = get_attribute(&attributes, "width").unwrap_or(0);
+                        image_layer.height = get_attribute(&attributes, "height").unwrap_or(0);
+                    } else if name.local_name == "property" && get_attribute::<String>(&attributes, "name").is_some_and(|name| name == PARALLAX_PROPERTY) {
+                        image_layer.parallax = get_attribute(&attributes, "value").unwrap_or(1.0);
+                    }
+                }
+            },
+            Ok(XmlEvent::EndElement{ name }) => {
+                if name.local_name == "imagelayer" {
+                    if let Some(image_layer) = current.take() {
+                        image_layers.push(image_layer);
+                    }
+                }
+            },
+            Ok(_) => {},
+            Err(_) => break,
+        }
+    }
+
+    image_layers
+}
+
+pub fn get_parallax(properties: &tiled::Properties) -> f32 {
+    match properties.get(PARALLAX_PROPERTY) {
+        Some(&tiled::PropertyValue::FloatValue(parallax)) => parallax,
+        Some(&tiled::PropertyValue::IntValue(parallax)) => parallax as f32,
+        _ => 1.0,
+    }
+}
+
+// used when a map doesn't place any spawn objects
+pub const DEFAULT_PLAYER_START: [i32; 2] = [0, 64];
 
 fn for_each_cell<F>(layer: &tiled::Layer, include_zero: bool, mut cb: F)
     where F: FnMut(usize, usize, u32)
@@ -37,17 +124,148 @@ fn add_column_above_to_ground(x: usize, y: usize, ground_tiles: &mut TileMapping
     }
 }
 
+pub struct MapRenderData<R: gfx::Resources> {
+    // repeating image layers, drawn behind everything
+    pub backdrops: Vec<BackdropRenderer<R>>,
+    // drawn before entities
+    pub background: Vec<TileMapRenderer<R>>,
+    // drawn after entities, so they can be in front of the player
+    pub foreground: Vec<TileMapRenderer<R>>,
+    // map layer index of each plane renderer, for updating tiles
+    background_layers: Vec<usize>,
+    foreground_layers: Vec<usize>,
+    image_layers: Vec<ImageLayer>,
+}
+
+impl<R> MapRenderData<R>
+    where R: gfx::Resources
+{
+    pub fn set_target(&mut self, target: &renderer::WindowTargets<R>) {
+        for backdrop in self.backdrops.iter_mut() {
+            backdrop.set_target(target);
+        }
+        for tilemap_renderer in self.background.iter_mut().chain(self.foreground.iter_mut()) {
+            tilemap_renderer.set_target(target);
+        }
+    }
+
+    /**
+     * Updates only the tiles that differ between the maps. Returns false when
+     * anything other than tiles changed, and the render data needs to be built again.
+     */
+    pub fn update_tiles(&mut self, old_map: &tiled::Map, map: &tiled::Map, image_layers: &[ImageLayer]) -> bool {
+        let same_layout = old_map.width == map.width && old_map.height == map.height &&
+            old_map.tile_width == map.tile_width && old_map.tile_height == map.tile_height &&
+            old_map.tilesets == map.tilesets && self.image_layers[..] == *image_layers &&
+            old_map.layers.len() == map.layers.len() &&
+            old_map.layers.iter().zip(map.layers.iter()).all(|(old_layer, layer)| {
+                old_layer.name == layer.name && old_layer.visible == layer.visible &&
+                    old_layer.opacity == layer.opacity && old_layer.properties == layer.properties
+            });
+        if !same_layout {
+            return false
+        }
+
+        let planes = self.background_layers.iter().zip(self.background.iter_mut())
+            .chain(self.foreground_layers.iter().zip(self.foreground.iter_mut()));
+        for (layer_index, tilemap_renderer) in planes {
+            let old_layer = &old_map.layers[*layer_index];
+            let layer = &map.layers[*layer_index];
+            for_each_cell(layer, true, |x, y, cell| {
+                if old_layer.tiles[y][x] != cell {
+                    tilemap_renderer.update_tile(map, layer, x, y);
+                }
+            });
+        }
+
+        true
+    }
+}
+
+/**
+ * A layer is in the foreground if it has the foreground property set,
+ * or if it comes after the "entities" marker tile layer. An object group of that name is ignored.
+ */
+fn is_foreground_layer(map: &tiled::Map, index: usize) -> bool {
+    let layer = &map.layers[index];
+    if let Some(&tiled::PropertyValue::BoolValue(foreground)) = layer.properties.get(FOREGROUND_PROPERTY) {
+        return foreground
+    }
+
+    map.layers.iter().position(|layer| layer.name == ENTITY_LAYER).is_some_and(|entity_index| index > entity_index)
+}
+
+/**
+ * Returns the indexes of the layers that get drawn, split into those behind and in front of entities.
+ */
+pub fn get_drawn_layers(map: &tiled::Map) -> (Vec<usize>, Vec<usize>) {
+    let mut background = Vec::new();
+    let mut foreground = Vec::new();
+    for (i, layer) in map.layers.iter().enumerate() {
+        if !layer.visible || layer.name == ENTITY_LAYER {
+            continue
+        }
+        if is_foreground_layer(map, i) {
+            foreground.push(i);
+        } else {
+            background.push(i);
+        }
+    }
+    (background, foreground)
+}
+
 pub fn get_map_render_data<R, F>(
     map: &tiled::Map,
     tiles_texture: &gfx::handle::ShaderResourceView<R, [f32; 4]>,
+    image_layers: &[(ImageLayer, gfx::handle::ShaderResourceView<R, [f32; 4]>)],
     factory: &mut F,
-    target: &renderer::WindowTargets<R>) -> Vec<PlaneRenderer<R>>
+    target: &renderer::WindowTargets<R>) -> MapRenderData<R>
     where R: gfx::Resources, F: gfx::Factory<R>
 {
-    map.layers.iter().map(|layer| {
-        let tilemap_plane = TileMapPlane::new(&map, &layer);
-        PlaneRenderer::new(factory, &tilemap_plane, tiles_texture, target)
-    }).collect::<Vec<PlaneRenderer<R>>>()
+    let map_height = (map.height * map.tile_height) as f32;
+    let (background_layers, foreground_layers) = get_drawn_layers(map);
+    MapRenderData{
+        backdrops: image_layers.iter()
+            .filter(|(image_layer, _)| image_layer.visible)
+            .map(|(image_layer, texture)| BackdropRenderer::new(factory, image_layer, map_height, texture, target))
+            .collect(),
+        background: background_layers.iter().map(|i| TileMapRenderer::new(factory, map, &map.layers[*i], tiles_texture, target)).collect(),
+        foreground: foreground_layers.iter().map(|i| TileMapRenderer::new(factory, map, &map.layers[*i], tiles_texture, target)).collect(),
+        background_layers,
+        foreground_layers,
+        image_layers: image_layers.iter().map(|(image_layer, _)| image_layer.clone()).collect(),
+    }
+}
+
+/**
+ * Returns world positions (bottom left, y up) of the objects in the "spawns" object group.
+ * Falls back to the default player start when the map has none.
+ */
+pub fn get_spawn_points(map: &tiled::Map) -> Vec<Vector2<i32>> {
+    let map_height = (map.height * map.tile_height) as f32;
+    let mut spawn_points: Vec<Vector2<i32>> = Vec::new();
+    for group in map.object_groups.iter().filter(|group| group.name == SPAWN_GROUP) {
+        for object in group.objects.iter() {
+            // tile objects are anchored at their bottom edge, everything else at the top
+            let bottom = match object.shape {
+                tiled::ObjectShape::Rect{ height, .. } if object.gid == 0 => object.y + height,
+                _ => object.y,
+            };
+            spawn_points.push(Vector2::new(object.x as i32, (map_height - bottom) as i32));
+        }
+    }
+
+    if spawn_points.is_empty() {
+        spawn_points.push(Vector2::new(DEFAULT_PLAYER_START[0], DEFAULT_PLAYER_START[1]));
+    }
+
+    spawn_points
+}
+
+pub fn build_tile_data(map: &tiled::Map) -> TileData {
+    let (walkable_groups, jump_targets, unpassable_tiles) = parse_out_map_layers(map);
+    let pathable_grid = astar::build_grid_for_map(&unpassable_tiles, map.width as usize, map.height as usize);
+    TileData::new(walkable_groups, map, jump_targets, pathable_grid)
 }
 
 pub fn parse_out_map_layers(map: &tiled::Map) -> (Vec<TileMapping<usize>>, TileMapping<usize>, TileMapping<usize>) {
@@ -155,6 +373,7 @@ pub fn parse_out_map_layers(map: &tiled::Map) -> (Vec<TileMapping<usize>>, TileM
     )
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use tiled::{Map, Layer, Tileset, Image, Tile, Properties};
@@ -233,4 +452,68 @@ mod tests {
         assert_eq!(jump_areas.contains_key(&(4)), false);
         assert_eq!(jump_areas.contains_key(&(5)), false);
     }
-}
\ No newline at end of file
+
+    fn build_layer(name: &str, properties: Properties) -> Layer {
+        Layer{
+            name: name.to_string(),
+            opacity: 1.0,
+            visible: true,
+            tiles: vec![vec![0]],
+            properties,
+        }
+    }
+
+    #[test]
+    fn test_is_foreground_layer() {
+        let mut properties = Properties::new();
+        properties.insert(FOREGROUND_PROPERTY.to_string(), tiled::PropertyValue::BoolValue(true));
+
+        let mut map = Map{
+            version: "1.0".to_string(),
+            orientation: tiled::Orientation::Orthogonal,
+            width: 1,
+            height: 1,
+            tile_width: 16,
+            tile_height: 16,
+            tilesets: Vec::new(),
+            layers: vec![build_layer("back", Properties::new()), build_layer("front", properties)],
+            object_groups: Vec::new(),
+            properties: Properties::new(),
+            background_colour: None,
+        };
+        assert!(!is_foreground_layer(&map, 0));
+        assert!(is_foreground_layer(&map, 1));
+
+        map.layers.insert(1, build_layer(ENTITY_LAYER, Properties::new()));
+        map.layers.push(build_layer("trees", Properties::new()));
+        assert!(!is_foreground_layer(&map, 0));
+        assert!(is_foreground_layer(&map, 3));
+    }
+
+    #[test]
+    fn test_parse_image_layers() {
+        let tmx = r#"<map>
+            <layer name="back" width="1" height="1"><data encoding="csv">0</data></layer>
+            <imagelayer name="sky" offsetx="16" opacity="0.5">
+                <properties><property name="parallax" type="float" value="0.25"/></properties>
+                <image source="sky.png" width="256" height="128"/>
+            </imagelayer>
+            <imagelayer name="hidden" visible="0"><image source="hills.png" width="64" height="64"/></imagelayer>
+        </map>"#;
+
+        let image_layers = parse_image_layers(tmx.as_bytes());
+        assert_eq!(image_layers.len(), 2);
+        assert_eq!(image_layers[0], ImageLayer{
+            name: "sky".to_string(),
+            source: "sky.png".to_string(),
+            width: 256,
+            height: 128,
+            offset: [16.0, 0.0],
+            opacity: 0.5,
+            visible: true,
+            parallax: 0.25,
+        });
+        assert!(!image_layers[1].visible);
+        assert_eq!(image_layers[1].parallax, 1.0);
+    }
+}
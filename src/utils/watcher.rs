@@ -0,0 +1,86 @@
+//! Polls a directory for modified files, used by dev mode to live reload resources.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+pub struct FileWatcher {
+    dir: PathBuf,
+    modified_times: HashMap<PathBuf, SystemTime>,
+    poll_interval: Duration,
+    last_poll: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(dir: &str, poll_interval: Duration) -> FileWatcher {
+        let mut watcher = FileWatcher{
+            dir: PathBuf::from(dir),
+            modified_times: HashMap::new(),
+            poll_interval,
+            last_poll: Instant::now(),
+        };
+        watcher.scan();
+        watcher
+    }
+
+    // returns files whose modified time differs from the last scan
+    fn scan(&mut self) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = Vec::new();
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return changed,
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let modified = match entry.metadata().and_then(|metadata| metadata.modified()) {
+                Ok(modified) => modified,
+                Err(_) => continue,
+            };
+            if self.modified_times.insert(path.clone(), modified) != Some(modified) {
+                changed.push(path);
+            }
+        }
+
+        changed
+    }
+
+    /**
+     * Returns the files changed since the last call. Only touches the file system once per poll interval.
+     */
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < self.poll_interval {
+            return Vec::new()
+        }
+        self.last_poll = Instant::now();
+        self.scan()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::process;
+
+    #[test]
+    fn test_poll() {
+        let dir = env::temp_dir().join(format!("watcher_test_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("map.tmx");
+        fs::write(&path, "<map/>").unwrap();
+
+        let mut watcher = FileWatcher::new(dir.to_str().unwrap(), Duration::from_millis(0));
+        assert!(watcher.poll().is_empty());
+
+        // set the time rather than rewriting, as file times can be too coarse to see a quick change
+        File::options().write(true).open(&path).unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10)).unwrap();
+        assert_eq!(watcher.poll(), vec![path]);
+        assert!(watcher.poll().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
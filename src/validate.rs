@@ -0,0 +1,262 @@
+//! Level validation, run with `agile_archer validate <path to tmx>`.
+//! Reports problems that would otherwise only show up while playing the map.
+
+extern crate tiled;
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use components::Player;
+use types::TileMapping;
+use utils;
+use utils::movement;
+
+// tiled stores flip flags in the top three bits of a gid
+const GID_MASK: u32 = 0x1FFF_FFFF;
+
+fn tiles_in(mapping: &TileMapping<usize>) -> Vec<(usize, usize)> {
+    let mut tiles: Vec<(usize, usize)> = Vec::new();
+    for (y, xs) in mapping.0.iter() {
+        for x in xs {
+            tiles.push((*x, *y));
+        }
+    }
+    tiles
+}
+
+fn within_distance(from: &[(usize, usize)], to: &(usize, usize), distance: usize) -> bool {
+    from.iter().any(|tile| movement::distance_to_tile(tile, to, false) <= distance)
+}
+
+fn get_tile_count(tileset: &tiled::Tileset) -> u32 {
+    if let Some(image) = tileset.images.first() {
+        let columns = (image.width as u32 + tileset.spacing).saturating_sub(tileset.margin * 2) / (tileset.tile_width + tileset.spacing);
+        let rows = (image.height as u32 + tileset.spacing).saturating_sub(tileset.margin * 2) / (tileset.tile_height + tileset.spacing);
+        columns * rows
+    } else {
+        tileset.tiles.len() as u32
+    }
+}
+
+fn check_tilesets(map: &tiled::Map, base_dir: &Path, errors: &mut Vec<String>) {
+    for tileset in map.tilesets.iter() {
+        if tileset.images.is_empty() {
+            errors.push(format!("Tileset \"{}\" has no image", tileset.name));
+        }
+        for image in tileset.images.iter() {
+            if !base_dir.join(&image.source).is_file() {
+                errors.push(format!("Tileset \"{}\" image not found: {}", tileset.name, image.source));
+            }
+        }
+    }
+}
+
+fn check_gids(map: &tiled::Map, errors: &mut Vec<String>) {
+    for layer in map.layers.iter() {
+        for (y, cols) in layer.tiles.iter().enumerate() {
+            for (x, cell) in cols.iter().enumerate() {
+                let gid = *cell & GID_MASK;
+                if gid == 0 {
+                    continue
+                }
+                let known = match map.get_tileset_by_gid(gid) {
+                    Some(tileset) => gid - tileset.first_gid < get_tile_count(tileset),
+                    None => false,
+                };
+                if !known {
+                    errors.push(format!("Layer \"{}\" has unknown gid {} at ({}, {})", layer.name, gid, x, y));
+                }
+            }
+        }
+    }
+}
+
+// the entities marker only works as a tile layer, as object groups aren't ordered among the tile layers
+fn check_entity_layer(map: &tiled::Map, errors: &mut Vec<String>) {
+    let is_tile_layer = map.layers.iter().any(|layer| layer.name == utils::tiled::ENTITY_LAYER);
+    if !is_tile_layer && map.object_groups.iter().any(|group| group.name == utils::tiled::ENTITY_LAYER) {
+        errors.push(format!("\"{}\" is an object group, it needs to be a tile layer to mark where entities are drawn", utils::tiled::ENTITY_LAYER));
+    }
+}
+
+/**
+ * Returns the indexes of walkable groups the player cannot get to from the start group.
+ * A group is reachable if a jump target is in jump distance of it, and of a reachable group.
+ */
+fn find_unreachable_groups(walkable_groups: &[TileMapping<usize>], jump_targets: &TileMapping<usize>, start_group: usize, jump_distance: usize) -> Vec<usize> {
+    let group_tiles: Vec<Vec<(usize, usize)>> = walkable_groups.iter().map(tiles_in).collect();
+    let jump_tiles = tiles_in(jump_targets);
+
+    let mut reached: HashSet<usize> = HashSet::new();
+    let mut open: Vec<usize> = vec![start_group];
+    reached.insert(start_group);
+
+    while let Some(index) = open.pop() {
+        let targets: Vec<&(usize, usize)> = jump_tiles.iter()
+            .filter(|target| within_distance(&group_tiles[index], target, jump_distance))
+            .collect();
+
+        for (i, tiles) in group_tiles.iter().enumerate() {
+            if reached.contains(&i) {
+                continue
+            }
+            if targets.iter().any(|target| within_distance(tiles, target, jump_distance)) {
+                reached.insert(i);
+                open.push(i);
+            }
+        }
+    }
+
+    (0..walkable_groups.len()).filter(|i| !reached.contains(i)).collect()
+}
+
+pub fn validate_map(map: &tiled::Map, base_dir: &Path) -> Vec<String> {
+    let mut errors: Vec<String> = Vec::new();
+
+    check_tilesets(map, base_dir, &mut errors);
+    check_gids(map, &mut errors);
+    check_entity_layer(map, &mut errors);
+
+    let mut tile_data = utils::tiled::build_tile_data(map);
+
+    let spawn_points = utils::tiled::get_spawn_points(map);
+    for spawn_point in spawn_points.iter() {
+        if !tile_data.set_player_group_index_from_pos(spawn_point) {
+            errors.push(format!("Spawn point not on ground: {:?}", spawn_point));
+        }
+    }
+
+    // the first spawn point is where the player starts
+    if tile_data.set_player_group_index_from_pos(&spawn_points[0]) {
+        let unreachable = find_unreachable_groups(&tile_data.walkable_groups, &tile_data.jump_targets, tile_data.player_group_index, Player::new().jump_distance);
+        for i in unreachable {
+            let mut tiles = tiles_in(&tile_data.walkable_groups[i]);
+            tiles.sort();
+            errors.push(format!("Walkable group {} starting at {:?} is unreachable from the start", i, tiles[0]));
+        }
+    }
+
+    errors
+}
+
+/**
+ * Validates the map at path, printing any errors. Returns the exit code for the process.
+ */
+pub fn run(path: &Path) -> i32 {
+    let map = match tiled::parse_file(path) {
+        Ok(map) => map,
+        Err(err) => {
+            println!("{}: could not parse map: {}", path.display(), err);
+            return 1
+        },
+    };
+
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let errors = validate_map(&map, base_dir);
+    for error in errors.iter() {
+        println!("{}: {}", path.display(), error);
+    }
+
+    if errors.is_empty() {
+        println!("{}: ok", path.display());
+        0
+    } else {
+        println!("{}: {} error(s)", path.display(), errors.len());
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiled::{Map, Layer, ObjectGroup, Tileset, Image, Properties};
+
+    fn build_map(tiles: Vec<Vec<u32>>) -> Map {
+        let image = Image{
+            source: "missing_image.png".to_string(),
+            width: 64,
+            height: 64,
+            transparent_colour: None,
+        };
+
+        let layer = Layer{
+            name: "ground".to_string(),
+            opacity: 1.0,
+            visible: true,
+            tiles,
+            properties: Properties::new(),
+        };
+
+        Map{
+            version: "1.0".to_string(),
+            orientation: tiled::Orientation::Orthogonal,
+            width: 8,
+            height: 6,
+            tile_width: 32,
+            tile_height: 32,
+            tilesets: vec![Tileset{ first_gid: 1, name: "Tileset".to_string(), tile_width: 32, tile_height: 32, spacing: 0, margin: 0, images: vec![image], tiles: Vec::new() }],
+            layers: vec![layer],
+            object_groups: Vec::new(),
+            properties: Properties::new(),
+            background_colour: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_map_reports_missing_image_and_unknown_gid() {
+        let map = build_map(vec![
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![1, 1, 1, 1, 1, 1, 1, 1],
+            vec![1, 1, 1, 1, 9, 1, 1, 1],
+        ]);
+        let errors = validate_map(&map, Path::new("/nonexistent"));
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("missing_image.png"));
+        assert!(errors[1].contains("unknown gid 9 at (4, 5)"));
+    }
+
+    #[test]
+    fn test_validate_map_reports_spawn_off_ground() {
+        let map = build_map(vec![
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 1, 1, 1, 1, 1],
+            vec![1, 1, 1, 1, 1, 1, 1, 1],
+        ]);
+        let errors = validate_map(&map, Path::new("/nonexistent"));
+        assert!(errors.iter().any(|error| error.starts_with("Spawn point not on ground")));
+    }
+
+    #[test]
+    fn test_validate_map_reports_entities_object_group() {
+        let mut map = build_map(vec![
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 0, 0, 0, 0],
+            vec![1, 1, 1, 1, 1, 1, 1, 1],
+            vec![1, 1, 1, 1, 1, 1, 1, 1],
+        ]);
+        map.object_groups.push(ObjectGroup{ name: "entities".to_string(), opacity: 1.0, visible: true, objects: Vec::new(), colour: None });
+        let errors = validate_map(&map, Path::new("/nonexistent"));
+        assert!(errors.iter().any(|error| error.contains("\"entities\" is an object group")));
+    }
+
+    #[test]
+    fn test_find_unreachable_groups() {
+        let mut near = TileMapping(::std::collections::HashMap::new());
+        near.insert(4, vec![0, 1]);
+        let mut far = TileMapping(::std::collections::HashMap::new());
+        far.insert(4, vec![20, 21]);
+        let mut jump_targets = TileMapping(::std::collections::HashMap::new());
+        jump_targets.insert(3, vec![2, 3, 4]);
+
+        let unreachable = find_unreachable_groups(&[near, far], &jump_targets, 0, 8);
+        assert_eq!(unreachable, vec![1]);
+    }
+}